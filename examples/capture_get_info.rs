@@ -0,0 +1,77 @@
+//! Capture `flickr.photos.getInfo` answers as test fixtures, with their credentials scrubbed
+//!
+//! ```sh
+//! cargo run --example capture_get_info -- tests/fixtures/get_info public_photo=53421987654
+//! ```
+use flickr_api::cassette::CassetteTransport;
+use flickr_api::*;
+use std::env;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+fn prompt(message: &str) -> String {
+    let mut input = String::new();
+
+    print!("{message}");
+    io::stdout().flush().ok();
+
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+
+    input.trim().to_string()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let directory = args
+        .next()
+        .expect("usage: capture_get_info DIRECTORY NAME=PHOTO_ID...");
+    let photos = args
+        .map(|arg| {
+            let (name, id) = arg
+                .split_once('=')
+                .expect("photos are given as NAME=PHOTO_ID");
+            (name.to_string(), PhotoId::from(id))
+        })
+        .collect::<Vec<_>>();
+
+    // Every interaction goes through the recorder, which scrubs the credentials
+    let recording = env::temp_dir().join("flickr-api-capture.json");
+    let cassette = Arc::new(CassetteTransport::record(
+        &recording,
+        ReqwestTransport::default(),
+    ));
+
+    // Logging in lets flickr answer with the fields only the owner sees
+    let client = FlickrAPI::builder(ApiKey {
+        key: prompt("API key: "),
+        secret: prompt("API secret: "),
+    })
+    .transport(cassette.clone())
+    .build()
+    .login_or_restore(&FileTokenStore::default_location()?)
+    .await?;
+
+    for (name, id) in photos {
+        client.photos().get_info(&id, None).await?;
+
+        let interaction = cassette
+            .cassette()
+            .interactions
+            .pop()
+            .expect("the answer was recorded");
+        let body = interaction.response.body.expect("flickr answers with text");
+
+        let path = Path::new(&directory).join(format!("{name}.json"));
+        std::fs::write(&path, body)?;
+        println!("Saved photo {id} to {path:?}");
+    }
+
+    std::fs::remove_file(recording).ok();
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use flickr_api::{ApiKey, FlickrAPI};
+use flickr_api::{ApiKey, FileTokenStore, FlickrAPI};
 use std::error::Error;
 use std::io::{self, Write};
 
@@ -27,7 +27,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         key: prompt("API key: "),
         secret: prompt("API secret: "),
     })
-    .login()
+    .login_or_restore(&FileTokenStore::default_location()?)
     .await?;
 
     let user = client.test().login().await?;
@@ -0,0 +1,51 @@
+use flickr_api::{ApiKey, FlickrAPI, Permission};
+use std::error::Error;
+use std::io::{self, Write};
+
+fn prompt(message: &str) -> String {
+    // Create a new String to hold the user's input
+    let mut input = String::new();
+
+    // Print a prompt message to the console
+    print!("{message}");
+
+    // Ensure the prompt message is printed immediately
+    io::stdout().flush().ok();
+
+    // Read the user's input from standard input
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+
+    // Remove the trailing newline character
+    input.trim().to_string()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let pending = FlickrAPI::new(ApiKey {
+        key: prompt("API key: "),
+        secret: prompt("API secret: "),
+    })
+    .begin_login(Permission::Read)
+    .await?;
+
+    println!("Open {} to grant access", pending.authorize_url());
+
+    // Ask again if the verifier was mistyped
+    let client = loop {
+        match pending.complete(&prompt("Verifier: ")).await {
+            Ok(client) => break client,
+            Err(flickr_api::Error::Oauth(problem)) => {
+                println!("Refused: {}", problem.oauth_problem)
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let user = client.test().login().await?;
+
+    println!("Successfully logged in as {} ({})", user.username, user.id);
+
+    Ok(())
+}
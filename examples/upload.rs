@@ -33,10 +33,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
         key: prompt("API key: "),
         secret: prompt("API secret: "),
     })
-    .login()
+    .login_or_restore(&FileTokenStore::default_location()?)
     .await?;
 
-    let id = client.photos().upload_from_path(&path).await?;
+    let id = client.photos().upload_from_path(path).await?;
     println!("Uploaded {path:?} and was given {id}");
 
     Ok(())
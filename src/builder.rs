@@ -0,0 +1,258 @@
+use crate::*;
+
+/// Base URLs of the services the client talks to
+///
+/// The defaults point to flickr. Overriding them allows targeting a local stand-in or going
+/// through a proxy path. Requests are signed against the URLs given here.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Endpoints {
+    /// OAuth request token endpoint
+    pub request: String,
+    /// OAuth authorization page the user is sent to
+    pub authorize: String,
+    /// OAuth access token endpoint
+    pub access: String,
+    /// REST API endpoint
+    pub api: String,
+    /// Upload endpoint
+    pub upload: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            request: URL_REQUEST.to_string(),
+            authorize: URL_AUTHORIZE.to_string(),
+            access: URL_ACCESS.to_string(),
+            api: URL_API.to_string(),
+            upload: URL_UPLOAD.to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Endpoints served from a single host, using the same paths as flickr
+    ///
+    /// `Endpoints::with_base("http://127.0.0.1:8080")` will send API calls to
+    /// `http://127.0.0.1:8080/services/rest/`.
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+
+        Endpoints {
+            request: format!("{base}/services/oauth/request_token"),
+            authorize: format!("{base}/services/oauth/authorize"),
+            access: format!("{base}/services/oauth/access_token"),
+            api: format!("{base}/services/rest/"),
+            upload: format!("{base}/services/upload/"),
+        }
+    }
+}
+
+/// Builder for a [FlickrAPI] client with non-default settings
+pub struct FlickrAPIBuilder {
+    key: ApiKey,
+    token: Option<OauthToken>,
+    endpoints: Endpoints,
+    signer: oauth::Signer,
+    authorization: AuthorizationMode,
+    retry: RetryPolicy,
+    rate_limit: Option<RateLimit>,
+    transport: Arc<dyn Transport>,
+    /// Set with [FlickrAPIBuilder::client], whose default user agent is then kept
+    custom_client: bool,
+    user_agent: Option<String>,
+}
+
+impl FlickrAPIBuilder {
+    pub fn new(key: ApiKey) -> Self {
+        FlickrAPIBuilder {
+            key,
+            token: None,
+            endpoints: Endpoints::default(),
+            signer: oauth::Signer::default(),
+            authorization: AuthorizationMode::default(),
+            retry: RetryPolicy::none(),
+            rate_limit: Some(RateLimit::default()),
+            transport: Arc::new(ReqwestTransport::default()),
+            custom_client: false,
+            user_agent: None,
+        }
+    }
+
+    /// Use an existing token instead of logging in
+    pub fn token(mut self, token: OauthToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Override the base URLs of the services
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Take the timestamp of requests from `clock` instead of the system's clock
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.signer = self.signer.with_clock(clock);
+        self
+    }
+
+    /// Take the nonce of requests from `nonces` instead of [RandomNonce]
+    ///
+    /// Meant for reproducible tests: flickr rejects requests reusing a nonce.
+    pub fn nonces(mut self, nonces: impl NonceSource + 'static) -> Self {
+        self.signer = self.signer.with_nonces(nonces);
+        self
+    }
+
+    /// Send the OAuth parameters of requests in an `Authorization` header instead of with the
+    /// other parameters
+    pub fn authorization(mut self, mode: AuthorizationMode) -> Self {
+        self.authorization = mode;
+        self
+    }
+
+    /// Send failed requests again according to `policy`. By default, requests are not retried
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Hold requests to `limit`, or send them as fast as possible with `None`. Defaults to
+    /// flickr's quota, see [RateLimit::default]
+    pub fn rate_limit(mut self, limit: impl Into<Option<RateLimit>>) -> Self {
+        self.rate_limit = limit.into();
+        self
+    }
+
+    /// Send requests with `client`, configured with a proxy, root certificates, timeouts...
+    ///
+    /// It is used for every request: API calls, uploads, logging in and downloads. Its default
+    /// user agent is kept unless [FlickrAPIBuilder::user_agent] is called.
+    pub fn client(self, client: reqwest::Client) -> Self {
+        FlickrAPIBuilder {
+            custom_client: true,
+            ..self.transport(ReqwestTransport::new(client))
+        }
+    }
+
+    /// Send requests through `transport` instead of the network, to answer them from tests
+    ///
+    /// Replaces the client set with [FlickrAPIBuilder::client].
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self.custom_client = false;
+        self
+    }
+
+    /// Identify requests with this `User-Agent` header instead of `flickr-api/<version>`, or the
+    /// default of the client given to [FlickrAPIBuilder::client]
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn build(self) -> FlickrAPI {
+        let data = Arc::new(FlickrAPIData {
+            transport: self.transport,
+            user_agent: match (self.user_agent, self.custom_client) {
+                (Some(user_agent), _) => Some(user_agent),
+                (None, true) => None,
+                (None, false) => Some(USER_AGENT.to_string()),
+            },
+            key: self.key,
+            token: self.token,
+            endpoints: self.endpoints,
+            signer: self.signer,
+            authorization: self.authorization,
+            retry: self.retry,
+            limiter: Arc::new(rate_limit::RateLimiter::new(self.rate_limit)),
+        });
+
+        FlickrAPI { data }
+    }
+}
+
+#[test]
+fn test_endpoints_with_base() {
+    let endpoints = Endpoints::with_base("http://127.0.0.1:8080/");
+
+    assert_eq!(
+        endpoints.request,
+        "http://127.0.0.1:8080/services/oauth/request_token"
+    );
+    assert_eq!(endpoints.api, "http://127.0.0.1:8080/services/rest/");
+    assert_eq!(endpoints.upload, "http://127.0.0.1:8080/services/upload/");
+}
+
+#[test]
+fn test_builder_endpoints() {
+    let client = FlickrAPI::builder(ApiKey::default())
+        .endpoints(Endpoints::with_base("http://localhost"))
+        .build();
+
+    assert_eq!(client.data.endpoints.api, "http://localhost/services/rest/");
+    assert_eq!(
+        FlickrAPI::new(ApiKey::default()).data.endpoints,
+        Endpoints::default()
+    );
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_builder_client_and_user_agent() {
+    use crate::mock::{test_key, MockPhoto, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(b"image".to_vec()));
+    let http = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let client = FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .token(server.issue_token(Permission::Write))
+        .client(http)
+        .user_agent("nightly-backup/1.0")
+        .build();
+
+    client.test().login().await.unwrap();
+    client.photos().upload(b"photo", None).await.unwrap();
+    let sizes = client.photos().get_sizes(&id).await.unwrap();
+    let original = sizes.iter().find(|size| size.label == "Original").unwrap();
+    client.download_image(&original.source).await.unwrap();
+
+    let agents = server.user_agents();
+    assert_eq!(agents.len(), 4);
+    assert!(agents.iter().all(|agent| agent == "nightly-backup/1.0"));
+
+    FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .build()
+        .test()
+        .login()
+        .await
+        .ok();
+    assert_eq!(
+        server.user_agents().last().unwrap(),
+        &format!("flickr-api/{}", env!("CARGO_PKG_VERSION"))
+    );
+
+    // The user agent configured on an injected client is kept
+    let http = reqwest::Client::builder()
+        .user_agent("configured-client/2.0")
+        .build()
+        .unwrap();
+    FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .client(http)
+        .build()
+        .test()
+        .login()
+        .await
+        .ok();
+    assert_eq!(
+        server.user_agents().last().unwrap(),
+        "configured-client/2.0"
+    );
+}
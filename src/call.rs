@@ -0,0 +1,404 @@
+use crate::*;
+use serde::de::DeserializeOwned;
+
+/// Parse the JSON answer of a REST method, turning `{"stat": "fail", ...}` into a [FlickrError]
+///
+/// `status` is the HTTP status of the answer, reported if the body could not be decoded.
+fn parse_answer<T: DeserializeOwned>(status: u16, raw: String) -> Result<T, Error> {
+    let value: Value = match serde_json::from_str(&raw) {
+        Ok(value) => value,
+        Err(_) if !(200..300).contains(&status) => return Err(Error::Http { status, body: raw }),
+        Err(source) => return Err(Error::Json { source, body: raw }),
+    };
+
+    if value["stat"] == "fail" {
+        return match FlickrError::deserialize(&value) {
+            Ok(error) => Err(Error::Api(error)),
+            Err(source) => Err(Error::Json { source, body: raw }),
+        };
+    }
+
+    T::deserialize(&value).map_err(|source| Error::Json { source, body: raw })
+}
+
+/// How a REST call is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verb {
+    /// Parameters in the query string. Safe to send again on failure
+    Get,
+    /// Parameters in a form-encoded body, without the URL length limit
+    Post,
+}
+
+impl Verb {
+    /// The verb [FlickrAPI::call] uses: flickr wants the methods changing data, the ones listed
+    /// as needing `write` or `delete`, to be POSTed
+    fn for_method(method: &str) -> Self {
+        match Permission::required_by(method) {
+            Permission::Read => Verb::Get,
+            Permission::Write | Permission::Delete => Verb::Post,
+        }
+    }
+}
+
+impl FlickrAPIData {
+    /// Start a request carrying the client's user agent, if it sets one
+    pub(crate) fn request(&self, method: &str, url: &str) -> HttpRequest {
+        let request = HttpRequest::new(method, url);
+
+        match &self.user_agent {
+            Some(user_agent) => request.header("User-Agent", user_agent),
+            None => request,
+        }
+    }
+
+    /// Send a request through the client's transport
+    pub(crate) async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        self.transport.send(request).await
+    }
+
+    /// Sign `params` for `target` with `token`
+    ///
+    /// Returns the parameters to send and, with [AuthorizationMode::Header], the value of the
+    /// `Authorization` header carrying the OAuth parameters removed from them.
+    pub(crate) fn sign<K: AsRef<str> + From<&'static str>>(
+        &self,
+        target: oauth::RequestTarget,
+        params: Vec<(K, String)>,
+        token: Option<&OauthToken>,
+    ) -> (Vec<(K, String)>, Option<String>) {
+        let mut params = params;
+        self.signer
+            .build_request(target, &mut params, &self.key, token);
+
+        let header = match self.authorization {
+            AuthorizationMode::Parameters => None,
+            AuthorizationMode::Header => Some(oauth::authorization_header(&mut params)),
+        };
+
+        (params, header)
+    }
+
+    /// Sign `params` with `token` and prepare the request carrying them to `target`
+    ///
+    /// Parameters go in the query string of GET requests and in the form-encoded body of POST
+    /// requests. With [AuthorizationMode::Header], the OAuth parameters go in the
+    /// `Authorization` header instead.
+    pub(crate) fn signed_request<K: AsRef<str> + From<&'static str>>(
+        &self,
+        target: oauth::RequestTarget,
+        params: Vec<(K, String)>,
+        token: Option<&OauthToken>,
+    ) -> Result<HttpRequest, Error> {
+        let (params, header) = self.sign(target, params, token);
+
+        let request = match target {
+            oauth::RequestTarget::Get(uri) => self.request(
+                "GET",
+                reqwest::Url::parse_with_params(uri, &params)?.as_str(),
+            ),
+            oauth::RequestTarget::Post(uri) => self.request("POST", uri).form(&params),
+        };
+
+        Ok(match header {
+            Some(header) => request.header("Authorization", &header),
+            None => request,
+        })
+    }
+
+    /// Sign and send a request to the REST endpoint, then parse the answer as `T`
+    pub(crate) async fn call_as<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<(&'static str, String)>,
+    ) -> Result<T, Error> {
+        self.call_with(Verb::for_method(method), method, params)
+            .await
+    }
+
+    /// Same as [FlickrAPIData::call_as], sent with `verb` and with parameter names only known at
+    /// runtime
+    async fn call_with<T, K>(
+        &self,
+        verb: Verb,
+        method: &str,
+        params: Vec<(K, String)>,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        K: AsRef<str> + From<&'static str> + Clone + Send + Sync,
+    {
+        self.check_permission(Permission::required_by(method))?;
+
+        let mut params = params;
+        params.extend(vec![
+            ("method".into(), method.to_string()),
+            ("format".into(), "json".into()),
+            ("nojsoncallback".into(), "1".into()),
+            ("api_key".into(), self.key.key.clone()),
+        ]);
+
+        let target = match verb {
+            Verb::Get => oauth::RequestTarget::Get(&self.endpoints.api),
+            Verb::Post => oauth::RequestTarget::Post(&self.endpoints.api),
+        };
+
+        let idempotent = verb == Verb::Get;
+
+        self.retry
+            .run(idempotent, || async {
+                self.limiter.acquire().await;
+                let fetch = self
+                    .send(self.signed_request(target, params.clone(), self.token.as_ref())?)
+                    .await?;
+                let status = fetch.status;
+                let raw = fetch.text();
+                #[cfg(debug_assertions)]
+                log::debug!("Received {raw}");
+
+                parse_answer(status, raw)
+            })
+            .await
+    }
+}
+
+impl FlickrAPI {
+    /// Call any method of the [REST API](https://www.flickr.com/services/api/) by name and return
+    /// the raw JSON answer.
+    ///
+    /// The request is signed with the API key and the token if present. `format`,
+    /// `nojsoncallback` and `api_key` are added automatically. A `{"stat": "fail"}` answer is
+    /// returned as a [FlickrError].
+    ///
+    /// Methods needing more than [Permission::Read], as listed by [Permission::required_by], are
+    /// sent as POST requests with a form-encoded body, others as GET requests. Use
+    /// [FlickrAPI::post] to POST other methods. If the token's access level is known, the call
+    /// fails without reaching flickr when the method is known to need more.
+    ///
+    /// `params` are pairs of names and values, such as `[("photo_id", id)]` or a `HashMap`.
+    pub async fn call<I, K, V>(&self, method: &str, params: I) -> Result<Value, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.call_as(method, params).await
+    }
+
+    /// Same as [FlickrAPI::call], deserializing the answer into `T`
+    pub async fn call_as<T, I, K, V>(&self, method: &str, params: I) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.data
+            .call_with(Verb::for_method(method), method, owned(params))
+            .await
+    }
+
+    /// Same as [FlickrAPI::call], always sent as a POST request
+    ///
+    /// For methods changing data that [Permission::required_by] does not list, or parameters too
+    /// long for a URL. POST requests are not retried.
+    pub async fn post<I, K, V>(&self, method: &str, params: I) -> Result<Value, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.post_as(method, params).await
+    }
+
+    /// Same as [FlickrAPI::post], deserializing the answer into `T`
+    pub async fn post_as<T, I, K, V>(&self, method: &str, params: I) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.data.call_with(Verb::Post, method, owned(params)).await
+    }
+}
+
+/// The names and values of `params` as strings
+fn owned<K: Into<String>, V: Into<String>>(
+    params: impl IntoIterator<Item = (K, V)>,
+) -> Vec<(String, String)> {
+    params
+        .into_iter()
+        .map(|(k, v)| (k.into(), v.into()))
+        .collect()
+}
+
+#[test]
+fn test_parse_answer_ok() {
+    let raw = r#"{"user":{"id":"12345678@N00","username":{"_content":"itsame"}},"stat":"ok"}"#;
+
+    let answer: Value = parse_answer(200, raw.into()).unwrap();
+
+    assert_eq!(answer["user"]["id"], "12345678@N00");
+}
+
+#[test]
+fn test_parse_answer_fail() {
+    let raw = r#"{"stat":"fail","code":112,"message":"Method \"flickr.nope\" not found"}"#;
+
+    match parse_answer::<Value>(200, raw.into()) {
+        Err(Error::Api(error)) => {
+            assert_eq!(error.code, ErrorCode::MethodNotFound);
+            assert_eq!(error.message, "Method \"flickr.nope\" not found");
+        }
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_answer_http() {
+    let raw = "<html><body>Bad Gateway</body></html>";
+
+    match parse_answer::<Value>(502, raw.into()) {
+        Err(Error::Http { status, body }) => {
+            assert_eq!(status, 502);
+            assert_eq!(body, raw);
+        }
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_answer_decode() {
+    let raw = r#"{"stat":"ok","user":{}}"#;
+
+    match parse_answer::<UserData>(200, raw.into()) {
+        Err(Error::Json { body, .. }) => assert_eq!(body, raw),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_call_posts_write_methods() {
+    use crate::mock::{test_key, MockPhoto, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(vec![]));
+    let client = server.logged_in(Permission::Write);
+
+    // Too long to fit in a URL
+    let description = "Ladies + Gentlemen, *vacances* à l'été ☀ & co. ".repeat(400);
+    client
+        .call(
+            "flickr.photos.setMeta",
+            vec![
+                ("photo_id", id.to_string()),
+                ("title", "Dogs, Cats & Mice".into()),
+                ("description", description.clone()),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let photo = server.photo(&id).unwrap();
+    assert_eq!(photo.title, "Dogs, Cats & Mice");
+    assert_eq!(photo.description, description);
+}
+
+#[tokio::test]
+async fn test_call_verb() {
+    use std::sync::Mutex;
+
+    /// Answers every request with an empty success, keeping their verb
+    #[derive(Default)]
+    struct Fake {
+        verbs: Mutex<Vec<String>>,
+    }
+
+    impl Transport for Fake {
+        fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+            self.verbs.lock().unwrap().push(request.method);
+
+            Box::pin(async {
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: vec![],
+                    body: br#"{"stat":"ok"}"#.to_vec(),
+                })
+            })
+        }
+    }
+
+    let fake = Arc::new(Fake::default());
+    let client = FlickrAPI::builder(ApiKey::default())
+        .transport(fake.clone())
+        .build();
+
+    client
+        .call("flickr.photos.getInfo", [("photo_id", "1")])
+        .await
+        .unwrap();
+    client
+        .call("flickr.photos.setMeta", [("photo_id", "1")])
+        .await
+        .unwrap();
+    // A method changing data the crate does not know about
+    client
+        .post("flickr.photos.setSomethingNew", [("photo_id", "1")])
+        .await
+        .unwrap();
+
+    assert_eq!(*fake.verbs.lock().unwrap(), ["GET", "POST", "POST"]);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_call_runtime_parameter_names() {
+    use crate::mock::{test_key, MockPhoto, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(vec![]));
+    let client = server.client();
+
+    // Names read from a configuration file, for example
+    let params: std::collections::HashMap<String, String> =
+        [(format!("photo_{}", "id"), id.to_string())].into();
+    let answer = client.call("flickr.photos.getInfo", params).await.unwrap();
+
+    assert_eq!(answer["photo"]["id"], id.as_str());
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_call_authorization_header() {
+    use crate::mock::{test_key, MockPhoto, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(vec![]));
+    let client = FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .token(server.issue_token(Permission::Write))
+        .authorization(AuthorizationMode::Header)
+        .build();
+
+    client.test().login().await.unwrap();
+    client
+        .call(
+            "flickr.photos.setMeta",
+            vec![
+                ("photo_id", id.to_string()),
+                ("title", "Header + body".into()),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(server.photo(&id).unwrap().title, "Header + body");
+
+    let uploaded = client
+        .photos()
+        .upload(b"not really a jpeg", Some("header.jpg".into()))
+        .await
+        .unwrap();
+    assert_eq!(server.photo(&uploaded).unwrap().title, "header");
+}
@@ -0,0 +1,753 @@
+//! Record the requests a client sends and their answers, then replay them without a network
+//!
+//! Run once with [CassetteTransport::record] against flickr to save the interactions in a JSON
+//! file, then use [CassetteTransport::replay] to answer the same requests offline:
+//!
+//! ```no_run
+//! # async fn run(key: flickr_api::ApiKey, token: flickr_api::OauthToken) -> Result<(), flickr_api::Error> {
+//! use flickr_api::cassette::CassetteTransport;
+//! use flickr_api::{FlickrAPI, ReqwestTransport};
+//!
+//! let path = "tests/cassettes/login.json";
+//! let cassette = match std::env::var_os("RECORD") {
+//!     Some(_) => CassetteTransport::record(path, ReqwestTransport::default()),
+//!     None => CassetteTransport::replay(path)?,
+//! };
+//!
+//! let client = FlickrAPI::builder(key)
+//!     .token(token)
+//!     .transport(cassette)
+//!     .build();
+//! let user = client.test().login().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are matched on their method, URL and parameters, ignoring the parameters that change
+//! with every request: `oauth_nonce`, `oauth_timestamp` and `oauth_signature`. Credentials such as
+//! `api_key` and `oauth_token` are replaced by [SCRUBBED] in the saved file, including in the
+//! fields of JSON and urlencoded answers and in the headers of answers. So are the tokens and
+//! token secrets flickr issues when logging in, so cassettes can be committed and replayed with
+//! any key. Only whole values are replaced: other bodies, such as XML, are saved as they are.
+use crate::*;
+use data_encoding::{BASE64, HEXLOWER};
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Value replacing credentials in cassettes
+pub const SCRUBBED: &str = "SCRUBBED";
+
+/// Parameters holding credentials, replaced by [SCRUBBED]
+const SENSITIVE: &[&str] = &[
+    "api_key",
+    "auth_token",
+    "oauth_consumer_key",
+    "oauth_token",
+    "oauth_verifier",
+];
+
+/// Credentials flickr hands out in its answers, as urlencoded fields or JSON keys
+const ISSUED: &[&str] = &["oauth_token", "oauth_token_secret"];
+
+/// Parameters that change with every request, left out when matching
+const VOLATILE: &[&str] = &["oauth_nonce", "oauth_signature", "oauth_timestamp"];
+
+/// A request, reduced to what is compared when replaying
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// URL without its query string
+    pub url: String,
+    /// Parameters from the query string, the body and the `Authorization` header, sorted. Files
+    /// are replaced by the SHA-1 of their contents
+    pub params: Vec<(String, String)>,
+}
+
+impl RecordedRequest {
+    fn new(request: &HttpRequest) -> Self {
+        let (url, params) = split_request(request);
+
+        let mut params = params
+            .into_iter()
+            .filter(|(k, _)| !VOLATILE.contains(&k.as_str()) && k != "realm")
+            .map(|(k, v)| match SENSITIVE.contains(&k.as_str()) {
+                true => (k, SCRUBBED.to_string()),
+                false => (k, v),
+            })
+            .collect::<Vec<_>>();
+        params.sort();
+
+        RecordedRequest {
+            method: request.method.to_uppercase(),
+            url,
+            params,
+        }
+    }
+}
+
+/// The URL of a request without its query string, and every parameter it carries
+fn split_request(request: &HttpRequest) -> (String, Vec<(String, String)>) {
+    let (url, mut params) = match url::Url::parse(&request.url) {
+        Ok(mut url) => {
+            let params = url.query_pairs().into_owned().collect::<Vec<_>>();
+            url.set_query(None);
+            (url.to_string(), params)
+        }
+        Err(_) => (request.url.clone(), vec![]),
+    };
+
+    let content_type = request.header_value("Content-Type").unwrap_or_default();
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        params.extend(url::form_urlencoded::parse(&request.body).into_owned());
+    } else if let Some(boundary) = content_type.strip_prefix("multipart/form-data; boundary=") {
+        params.extend(multipart_fields(&request.body, boundary));
+    }
+
+    if let Some(fields) = request
+        .header_value("Authorization")
+        .and_then(|header| header.strip_prefix("OAuth "))
+    {
+        let encoded = fields
+            .split(',')
+            .filter_map(|field| field.trim().split_once('='))
+            .map(|(k, v)| format!("{k}={}", v.trim_matches('"')))
+            .collect::<Vec<_>>()
+            .join("&");
+        params.extend(url::form_urlencoded::parse(encoded.as_bytes()).into_owned());
+    }
+
+    (url, params)
+}
+
+/// Values of the credentials sent with a request
+fn sensitive_values(request: &HttpRequest) -> Vec<String> {
+    split_request(request)
+        .1
+        .into_iter()
+        .filter(|(k, _)| SENSITIVE.contains(&k.as_str()))
+        .map(|(_, v)| v)
+        .collect()
+}
+
+/// Whether the field `name` holding `value` is a credential: a field known to hold one, or one
+/// of the `secrets` sent with the request
+fn is_secret(name: &str, value: &str, secrets: &[String]) -> bool {
+    SENSITIVE.contains(&name)
+        || ISSUED.contains(&name)
+        || secrets
+            .iter()
+            .any(|secret| !secret.is_empty() && secret == value)
+}
+
+/// Replace the credentials among the string values of a JSON answer, returning whether any was
+///
+/// `name` is the field holding `value`, also used for `{"_content": ...}` wrappers.
+fn scrub_json(value: &mut Value, name: &str, secrets: &[String]) -> bool {
+    match value {
+        Value::String(text) if is_secret(name, text, secrets) => {
+            *text = SCRUBBED.to_string();
+            true
+        }
+        Value::Object(fields) => {
+            let mut scrubbed = false;
+            for (k, v) in fields {
+                let name = if k == "_content" { name } else { k };
+                scrubbed |= scrub_json(v, name, secrets);
+            }
+            scrubbed
+        }
+        Value::Array(items) => {
+            let mut scrubbed = false;
+            for item in items {
+                scrubbed |= scrub_json(item, name, secrets);
+            }
+            scrubbed
+        }
+        _ => false,
+    }
+}
+
+/// Replace the credentials among urlencoded pairs, such as
+/// `oauth_token=...&oauth_token_secret=...`, or `None` if there are none
+fn scrub_pairs(text: &str, secrets: &[String]) -> Option<String> {
+    let pairs = url::form_urlencoded::parse(text.as_bytes())
+        .into_owned()
+        .collect::<Vec<_>>();
+
+    if !pairs.iter().any(|(k, v)| is_secret(k, v, secrets)) {
+        return None;
+    }
+
+    let scrubbed = pairs.iter().map(|(k, v)| match is_secret(k, v, secrets) {
+        true => (k.as_str(), SCRUBBED),
+        false => (k.as_str(), v.as_str()),
+    });
+
+    Some(
+        url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(scrubbed)
+            .finish(),
+    )
+}
+
+/// A text body with its credentials replaced, if it is JSON or urlencoded. Other bodies are kept
+/// as they are
+fn scrub_body(text: String, secrets: &[String]) -> String {
+    if let Ok(mut value) = serde_json::from_str::<Value>(&text) {
+        return match scrub_json(&mut value, "", secrets) {
+            true => value.to_string(),
+            false => text,
+        };
+    }
+
+    let urlencoded =
+        text.contains('=') && !text.contains(|c: char| c.is_whitespace() || "<>{}\"".contains(c));
+    match urlencoded {
+        true => scrub_pairs(&text, secrets).unwrap_or(text),
+        false => text,
+    }
+}
+
+/// A header value with its credentials replaced: the whole value, or the parameters of a URL
+/// such as the target of a redirection
+fn scrub_header(value: &str, secrets: &[String]) -> String {
+    if is_secret("", value, secrets) {
+        return SCRUBBED.to_string();
+    }
+
+    match url::Url::parse(value) {
+        Ok(mut url) => match url.query().and_then(|query| scrub_pairs(query, secrets)) {
+            Some(query) => {
+                url.set_query(Some(&query));
+                url.to_string()
+            }
+            None => value.to_string(),
+        },
+        Err(_) => value.to_string(),
+    }
+}
+
+/// Text fields of a multipart body, with files replaced by their SHA-1
+fn multipart_fields(body: &[u8], boundary: &str) -> Vec<(String, String)> {
+    let delimiter = format!("--{boundary}").into_bytes();
+    let mut fields = vec![];
+
+    for part in split(body, &delimiter).into_iter().skip(1) {
+        let Some(end) = part.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&part[..end]);
+        let content = part[end + 4..]
+            .strip_suffix(b"\r\n")
+            .unwrap_or(&part[end + 4..]);
+
+        let attribute = |name: &str| {
+            let start = headers.find(&format!("; {name}=\""))? + name.len() + 4;
+            let length = headers[start..].find('"')?;
+            Some(headers[start..start + length].to_string())
+        };
+
+        let Some(name) = attribute("name") else {
+            continue;
+        };
+        match attribute("filename") {
+            Some(_) => fields.push((
+                name,
+                format!("sha1:{}", HEXLOWER.encode(&Sha1::digest(content))),
+            )),
+            None => fields.push((name, String::from_utf8_lossy(content).into_owned())),
+        }
+    }
+
+    fields
+}
+
+fn split<'a>(data: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = vec![];
+    let mut rest = data;
+
+    while let Some(index) = rest.windows(delimiter.len()).position(|w| w == delimiter) {
+        parts.push(&rest[..index]);
+        rest = &rest[index + delimiter.len()..];
+    }
+    parts.push(rest);
+
+    parts
+}
+
+/// An answer as saved in a cassette
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The body as text, unless it is not valid UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// The body encoded in base64, if it is not valid UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+impl RecordedResponse {
+    /// Save `response`, replacing the credentials it holds: the `secrets` sent with the request
+    /// and the tokens it issues, in its headers and its JSON or urlencoded body
+    fn new(response: &HttpResponse, secrets: &[String]) -> Self {
+        let (body, body_base64) = match String::from_utf8(response.body.clone()) {
+            Ok(text) => (Some(scrub_body(text, secrets)), None),
+            Err(_) => (None, Some(BASE64.encode(&response.body))),
+        };
+
+        RecordedResponse {
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .map(|(k, v)| (k.clone(), scrub_header(v, secrets)))
+                .collect(),
+            body,
+            body_base64,
+        }
+    }
+
+    fn to_response(&self) -> Result<HttpResponse, Error> {
+        let body = match (&self.body, &self.body_base64) {
+            (_, Some(encoded)) => BASE64
+                .decode(encoded.as_bytes())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
+            (Some(text), None) => text.clone().into_bytes(),
+            (None, None) => vec![],
+        };
+
+        Ok(HttpResponse {
+            status: self.status,
+            headers: self.headers.clone(),
+            body,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// The contents of a cassette file
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let body = std::fs::read_to_string(path)?;
+
+        serde_json::from_str(&body).map_err(|source| Error::Json { source, body })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let body = serde_json::to_string_pretty(self).expect("cassettes serialize to JSON");
+        std::fs::write(path, body + "\n")?;
+
+        Ok(())
+    }
+}
+
+enum Mode {
+    /// Send requests with the inner transport and save them to the path
+    Record {
+        inner: Box<dyn Transport>,
+        path: PathBuf,
+    },
+    /// Answer from the cassette
+    Replay,
+}
+
+struct State {
+    cassette: Cassette,
+    /// Which interactions were already replayed
+    used: Vec<bool>,
+}
+
+/// A [Transport] recording interactions to a cassette file, or answering from one
+pub struct CassetteTransport {
+    mode: Mode,
+    state: Mutex<State>,
+}
+
+impl CassetteTransport {
+    /// Send requests with `inner` and save every interaction to `path`, replacing its contents
+    ///
+    /// The file is written after each interaction.
+    pub fn record(path: impl Into<PathBuf>, inner: impl Transport + 'static) -> Self {
+        CassetteTransport {
+            mode: Mode::Record {
+                inner: Box::new(inner),
+                path: path.into(),
+            },
+            state: Mutex::new(State {
+                cassette: Cassette::default(),
+                used: vec![],
+            }),
+        }
+    }
+
+    /// Answer requests from the cassette saved at `path`
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(CassetteTransport::from_cassette(Cassette::load(
+            path.as_ref(),
+        )?))
+    }
+
+    /// Answer requests from `cassette`
+    pub fn from_cassette(cassette: Cassette) -> Self {
+        CassetteTransport {
+            mode: Mode::Replay,
+            state: Mutex::new(State {
+                used: vec![false; cassette.interactions.len()],
+                cassette,
+            }),
+        }
+    }
+
+    /// The interactions recorded or loaded so far
+    pub fn cassette(&self) -> Cassette {
+        self.state.lock().unwrap().cassette.clone()
+    }
+
+    /// Find the answer to `request`: the first unused matching interaction, or the last
+    /// matching one if they were all used
+    fn replay_answer(&self, request: &RecordedRequest) -> Result<HttpResponse, Error> {
+        let mut state = self.state.lock().unwrap();
+
+        let matching = state
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| &interaction.request == request)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let index = matching
+            .iter()
+            .find(|index| !state.used[**index])
+            .or(matching.last())
+            .copied()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!(
+                        "no interaction recorded for {} {} {:?}",
+                        request.method, request.url, request.params
+                    ),
+                )
+            })?;
+        state.used[index] = true;
+
+        state.cassette.interactions[index].response.to_response()
+    }
+
+    /// Save an interaction and write the cassette to `path`
+    fn record_answer(
+        &self,
+        path: &Path,
+        request: RecordedRequest,
+        response: RecordedResponse,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .cassette
+            .interactions
+            .push(Interaction { request, response });
+        state.used.push(true);
+
+        state.cassette.save(path)
+    }
+}
+
+impl Transport for CassetteTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let recorded = RecordedRequest::new(&request);
+
+            let Mode::Record { inner, path } = &self.mode else {
+                return self.replay_answer(&recorded);
+            };
+
+            let secrets = sensitive_values(&request);
+            let response = inner.send(request).await?;
+            self.record_answer(path, recorded, RecordedResponse::new(&response, &secrets))?;
+
+            Ok(response)
+        })
+    }
+}
+
+#[test]
+fn test_recorded_request_ignores_volatile_parameters() {
+    let signer = oauth::Signer::default();
+    let key = ApiKey {
+        key: "0123456789abcdef".into(),
+        secret: "fedcba9876543210".into(),
+    };
+    let sign = |target| {
+        let mut params = vec![("method", "flickr.test.login".to_string())];
+        signer.build_request(target, &mut params, &key, None);
+        params
+    };
+
+    let url = "https://api.flickr.com/services/rest/";
+    let get = |params: Vec<(&str, String)>| {
+        HttpRequest::new(
+            "GET",
+            reqwest::Url::parse_with_params(url, &params)
+                .unwrap()
+                .as_str(),
+        )
+    };
+    let first = get(sign(oauth::RequestTarget::Get(url)));
+    let second = get(sign(oauth::RequestTarget::Get(url)));
+    assert_ne!(first, second);
+
+    let recorded = RecordedRequest::new(&first);
+    assert_eq!(recorded, RecordedRequest::new(&second));
+    assert_eq!(recorded.url, url);
+    assert_eq!(
+        recorded.params,
+        vec![
+            ("method".to_string(), "flickr.test.login".to_string()),
+            ("oauth_consumer_key".to_string(), SCRUBBED.to_string()),
+            (
+                "oauth_signature_method".to_string(),
+                "HMAC-SHA1".to_string()
+            ),
+            ("oauth_version".to_string(), "1.0".to_string()),
+        ]
+    );
+
+    // The same parameters sent in a form or headers match too
+    let mut params = sign(oauth::RequestTarget::Post(url));
+    let header = oauth::authorization_header(&mut params);
+    let post = HttpRequest::new("POST", url)
+        .form(&params)
+        .header("Authorization", &header);
+    assert_eq!(
+        RecordedRequest::new(&post),
+        RecordedRequest {
+            method: "POST".into(),
+            ..recorded
+        }
+    );
+}
+
+#[test]
+fn test_recorded_request_multipart() {
+    let request = HttpRequest::new("POST", "https://up.flickr.com/services/upload/").multipart(
+        &[("oauth_token", "secret-token"), ("title", "Holidays")],
+        "photo",
+        "holidays.jpg",
+        b"data",
+    );
+
+    assert_eq!(
+        RecordedRequest::new(&request).params,
+        vec![
+            ("oauth_token".to_string(), SCRUBBED.to_string()),
+            (
+                "photo".to_string(),
+                "sha1:a17c9aaa61e80a1bf71d0d850af4e5baa9800bbd".to_string()
+            ),
+            ("title".to_string(), "Holidays".to_string()),
+        ]
+    );
+    assert_eq!(sensitive_values(&request), vec!["secret-token"]);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_cassette_record_and_replay() {
+    use crate::mock::{test_key, MockPhoto, MockServer};
+
+    let path = std::env::temp_dir()
+        .join(format!("flickr-api-cassette-{}", std::process::id()))
+        .join("session.json");
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(vec![]));
+    let token = server.issue_token(Permission::Write);
+    let endpoints = server.endpoints();
+
+    let client = FlickrAPI::builder(test_key())
+        .endpoints(endpoints.clone())
+        .token(token.clone())
+        .transport(CassetteTransport::record(
+            &path,
+            ReqwestTransport::default(),
+        ))
+        .build();
+    let recorded_user = client.test().login().await.unwrap();
+    let recorded_info = client.photos().get_info(&id, None).await.unwrap();
+    let recorded_upload = client.photos().upload(b"photo", None).await.unwrap();
+    drop(server);
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(!saved.contains(&token.token));
+    assert!(!saved.contains("0123456789abcdef"));
+
+    // Replayed with other credentials, once the server is gone
+    let client = FlickrAPI::builder(ApiKey {
+        key: "another key".into(),
+        secret: "another secret".into(),
+    })
+    .endpoints(endpoints)
+    .token(OauthToken {
+        token: "another token".into(),
+        ..token
+    })
+    .transport(CassetteTransport::replay(&path).unwrap())
+    .build();
+
+    assert_eq!(client.test().login().await.unwrap().id, recorded_user.id);
+    assert_eq!(
+        client.photos().get_info(&id, None).await.unwrap().id,
+        recorded_info.id
+    );
+    assert_eq!(
+        client.photos().upload(b"photo", None).await.unwrap(),
+        recorded_upload
+    );
+    match client.photos().get_info("unknown", None).await {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_issued_credentials_scrubbed() {
+    let answer = |body: &str| HttpResponse {
+        status: 200,
+        headers: vec![],
+        body: body.as_bytes().to_vec(),
+    };
+
+    let form = RecordedResponse::new(
+        &answer("oauth_callback_confirmed=true&oauth_token=72157-abc&oauth_token_secret=s%2Fcret"),
+        &[],
+    );
+    assert_eq!(
+        form.body.as_deref(),
+        Some("oauth_callback_confirmed=true&oauth_token=SCRUBBED&oauth_token_secret=SCRUBBED")
+    );
+
+    let json = RecordedResponse::new(
+        &answer(
+            r#"{"auth":{"access_token":{"oauth_token":"72157-def","oauth_token_secret":"0a1b2c"}},"stat":"ok"}"#,
+        ),
+        &[],
+    );
+    assert_eq!(
+        json.body.as_deref(),
+        Some(
+            r#"{"auth":{"access_token":{"oauth_token":"SCRUBBED","oauth_token_secret":"SCRUBBED"}},"stat":"ok"}"#
+        )
+    );
+}
+
+#[test]
+fn test_scrub_whole_values() {
+    let secrets = ["12".to_string(), "72157-abc".to_string()];
+    let response = HttpResponse {
+        status: 302,
+        headers: vec![
+            (
+                "Location".into(),
+                "http://localhost:8200/done?oauth_token=72157-abc&oauth_verifier=123-42-7".into(),
+            ),
+            ("Content-Length".into(), "1234".into()),
+        ],
+        body: br#"{"photo":{"id":"1234","secret":"ab12"},"token":{"_content":"12"}}"#.to_vec(),
+    };
+
+    let recorded = RecordedResponse::new(&response, &secrets);
+
+    // A short secret is not replaced inside unrelated values
+    assert_eq!(
+        recorded.body.as_deref(),
+        Some(r#"{"photo":{"id":"1234","secret":"ab12"},"token":{"_content":"SCRUBBED"}}"#)
+    );
+    assert_eq!(
+        recorded.headers,
+        vec![
+            (
+                "Location".to_string(),
+                "http://localhost:8200/done?oauth_token=SCRUBBED&oauth_verifier=SCRUBBED"
+                    .to_string()
+            ),
+            ("Content-Length".to_string(), "1234".to_string()),
+        ]
+    );
+
+    let xml = r#"<rsp stat="ok"><photoid>12</photoid></rsp>"#;
+    let recorded = RecordedResponse::new(
+        &HttpResponse {
+            status: 200,
+            headers: vec![],
+            body: xml.as_bytes().to_vec(),
+        },
+        &secrets,
+    );
+    assert_eq!(recorded.body.as_deref(), Some(xml));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_cassette_scrubs_login() {
+    use crate::mock::{test_key, MockServer};
+
+    let path = std::env::temp_dir()
+        .join(format!("flickr-api-cassette-login-{}", std::process::id()))
+        .join("login.json");
+    let server = MockServer::start(test_key()).await;
+    let client = FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .transport(CassetteTransport::record(
+            &path,
+            ReqwestTransport::default(),
+        ))
+        .build();
+
+    let pending = client.begin_login(Permission::Write).await.unwrap();
+    let verifier = server.authorize(pending.authorize_url()).await;
+    let client = pending.complete(&verifier).await.unwrap();
+
+    let legacy = server.issue_legacy_token(Permission::Read);
+    let exchanged = client.auth().get_access_token(&legacy).await.unwrap();
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    let token = client.token().unwrap();
+    for secret in [
+        &token.token,
+        &token.secret,
+        &exchanged.token,
+        &exchanged.secret,
+    ] {
+        assert!(
+            !saved.contains(secret.as_str()),
+            "{secret} saved in the cassette"
+        );
+    }
+    assert!(saved.contains("oauth_token_secret=SCRUBBED"));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
@@ -0,0 +1,74 @@
+use crate::*;
+
+#[derive(Deserialize, Debug)]
+struct CheckTokenAnswerSuccess {
+    oauth: OauthCheck,
+}
+
+#[derive(Deserialize, Debug)]
+struct OauthCheck {
+    #[serde(deserialize_with = "deserialize_content")]
+    token: String,
+    perms: PermsContent,
+    user: Identity,
+}
+
+#[derive(Deserialize, Debug)]
+struct PermsContent {
+    _content: Permission,
+}
+
+/// Credentials attached to a token, as returned by flickr
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TokenCheck {
+    pub token: String,
+    /// The access level granted to the token
+    pub perms: Permission,
+    /// The account the token was issued for
+    pub user: Identity,
+}
+
+impl AuthRequestBuilder {
+    /// [flickr.auth.oauth.checkToken](https://www.flickr.com/services/api/flickr.auth.oauth.checkToken.html)
+    /// endpoint. Returns the permissions and user attached to the client's token.
+    pub async fn check_token(&self) -> Result<TokenCheck, Error> {
+        // The token is sent as the `oauth_token` parameter the method expects
+        let answer: CheckTokenAnswerSuccess = self
+            .handle
+            .call_as("flickr.auth.oauth.checkToken", vec![])
+            .await?;
+
+        Ok(TokenCheck {
+            token: answer.oauth.token,
+            perms: answer.oauth.perms._content,
+            user: answer.oauth.user,
+        })
+    }
+}
+
+#[test]
+fn test_check_token_answer() {
+    let raw = r#"{"oauth":{"token":{"_content":"72157626318069415-087bfc7b5816092c"},"perms":{"_content":"write"},"user":{"nsid":"21207597@N07","username":"jamalfanaian","fullname":"Jamal Fanaian"}},"stat":"ok"}"#;
+
+    let answer: CheckTokenAnswerSuccess = serde_json::from_str(raw).unwrap();
+
+    assert_eq!(answer.oauth.token, "72157626318069415-087bfc7b5816092c");
+    assert_eq!(answer.oauth.perms._content, Permission::Write);
+    assert_eq!(answer.oauth.user.nsid, "21207597@N07");
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_check_token() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let token = server.issue_token(Permission::Delete);
+    let client = server.client().with_token(token.clone());
+
+    let check = client.auth().check_token().await.unwrap();
+
+    assert_eq!(check.token, token.token);
+    assert_eq!(check.perms, Permission::Delete);
+    assert_eq!(check.user.nsid, server.user().nsid);
+}
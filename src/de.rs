@@ -0,0 +1,131 @@
+//! Tolerant deserializers for the values flickr sends either as JSON numbers or as strings
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A scalar as flickr may send it
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl Scalar {
+    fn text(&self) -> String {
+        match self {
+            Scalar::Bool(value) => value.to_string(),
+            Scalar::Unsigned(value) => value.to_string(),
+            Scalar::Signed(value) => value.to_string(),
+            Scalar::Float(value) => value.to_string(),
+            Scalar::Text(value) => value.trim().to_string(),
+        }
+    }
+}
+
+/// `123` or `"123"`. An empty string is `0`
+pub(crate) fn u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match Scalar::deserialize(deserializer)? {
+        Scalar::Unsigned(value) => Ok(value),
+        Scalar::Text(text) if text.trim().is_empty() => Ok(0),
+        other => other
+            .text()
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid number: {}", other.text()))),
+    }
+}
+
+/// `123` or `"123"`. An empty string is `0`
+pub(crate) fn u32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    u64(deserializer)
+        .and_then(|value| u32::try_from(value).map_err(|_| D::Error::custom("number too large")))
+}
+
+/// `1.5` or `"1.5"`. An empty string is `0`
+pub(crate) fn f64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    match Scalar::deserialize(deserializer)? {
+        Scalar::Unsigned(value) => Ok(value as f64),
+        Scalar::Signed(value) => Ok(value as f64),
+        Scalar::Float(value) => Ok(value),
+        Scalar::Text(text) if text.trim().is_empty() => Ok(0.0),
+        other => other
+            .text()
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid number: {}", other.text()))),
+    }
+}
+
+/// `true`, `1`, `"1"` or `"true"`, and their opposites. An empty string is `false`
+pub(crate) fn bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let scalar = Scalar::deserialize(deserializer)?;
+
+    match scalar.text().as_str() {
+        "1" | "true" => Ok(true),
+        "0" | "false" | "" => Ok(false),
+        other => Err(D::Error::custom(format!("invalid flag: {other}"))),
+    }
+}
+
+/// Seconds since the Unix epoch, read as a number or a string and written as a number
+pub(crate) mod timestamp {
+    use super::*;
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SystemTime, D::Error> {
+        u64(deserializer).map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        time: &SystemTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| serde::ser::Error::custom("date before the Unix epoch"))?;
+
+        serializer.serialize_u64(seconds.as_secs())
+    }
+}
+
+#[test]
+fn test_tolerant_numbers() {
+    #[derive(Deserialize)]
+    struct Numbers {
+        #[serde(deserialize_with = "u64")]
+        a: u64,
+        #[serde(deserialize_with = "u64")]
+        b: u64,
+        #[serde(deserialize_with = "u32")]
+        c: u32,
+        #[serde(deserialize_with = "f64")]
+        d: f64,
+        #[serde(deserialize_with = "f64")]
+        e: f64,
+        #[serde(deserialize_with = "bool")]
+        f: bool,
+        #[serde(deserialize_with = "bool")]
+        g: bool,
+        #[serde(with = "timestamp")]
+        h: SystemTime,
+    }
+
+    let numbers: Numbers = serde_json::from_str(
+        r#"{"a": 12, "b": " 34 ", "c": "", "d": "-41.5", "e": 2, "f": "1", "g": 0, "h": "1700000000"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(numbers.a, 12);
+    assert_eq!(numbers.b, 34);
+    assert_eq!(numbers.c, 0);
+    assert_eq!(numbers.d, -41.5);
+    assert_eq!(numbers.e, 2.0);
+    assert!(numbers.f);
+    assert!(!numbers.g);
+    assert_eq!(numbers.h, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+    assert!(serde_json::from_str::<Numbers>(r#"{"a": "many"}"#).is_err());
+}
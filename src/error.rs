@@ -0,0 +1,298 @@
+use crate::oauth::{OauthErrorDescription, Permission};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Error codes returned by the flickr API
+///
+/// Codes above 95 are common to every method and are listed
+/// [here](https://www.flickr.com/services/api/misc.api_errors.html). Codes below are specific to
+/// the method called and must be interpreted using its documentation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(from = "u32", into = "u32")]
+pub enum ErrorCode {
+    /// Meaning depends on the method, e.g. `1` is "Photo not found" for `flickr.photos.getInfo`
+    MethodSpecific(u32),
+    SslRequired,
+    InvalidSignature,
+    MissingSignature,
+    /// The token is invalid or expired
+    InvalidAuthToken,
+    /// The user is not logged in or the token lacks the permission the method requires
+    InsufficientPermissions,
+    InvalidApiKey,
+    ServiceUnavailable,
+    WriteOperationFailed,
+    FormatNotFound,
+    MethodNotFound,
+    InvalidSoapEnvelope,
+    InvalidXmlRpcCall,
+    BadUrl,
+    /// A common code this crate does not know about
+    Unknown(u32),
+}
+
+impl ErrorCode {
+    /// The numeric value of the code as sent by flickr
+    pub fn code(&self) -> u32 {
+        match self {
+            ErrorCode::MethodSpecific(code) | ErrorCode::Unknown(code) => *code,
+            ErrorCode::SslRequired => 95,
+            ErrorCode::InvalidSignature => 96,
+            ErrorCode::MissingSignature => 97,
+            ErrorCode::InvalidAuthToken => 98,
+            ErrorCode::InsufficientPermissions => 99,
+            ErrorCode::InvalidApiKey => 100,
+            ErrorCode::ServiceUnavailable => 105,
+            ErrorCode::WriteOperationFailed => 106,
+            ErrorCode::FormatNotFound => 111,
+            ErrorCode::MethodNotFound => 112,
+            ErrorCode::InvalidSoapEnvelope => 114,
+            ErrorCode::InvalidXmlRpcCall => 115,
+            ErrorCode::BadUrl => 116,
+        }
+    }
+}
+
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            95 => ErrorCode::SslRequired,
+            96 => ErrorCode::InvalidSignature,
+            97 => ErrorCode::MissingSignature,
+            98 => ErrorCode::InvalidAuthToken,
+            99 => ErrorCode::InsufficientPermissions,
+            100 => ErrorCode::InvalidApiKey,
+            105 => ErrorCode::ServiceUnavailable,
+            106 => ErrorCode::WriteOperationFailed,
+            111 => ErrorCode::FormatNotFound,
+            112 => ErrorCode::MethodNotFound,
+            114 => ErrorCode::InvalidSoapEnvelope,
+            115 => ErrorCode::InvalidXmlRpcCall,
+            116 => ErrorCode::BadUrl,
+            code if code < 95 => ErrorCode::MethodSpecific(code),
+            code => ErrorCode::Unknown(code),
+        }
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(code: ErrorCode) -> Self {
+        code.code()
+    }
+}
+
+/// Common error type for all flickr API answers
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlickrError {
+    pub stat: String,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl std::error::Error for FlickrError {}
+
+impl Display for FlickrError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(formatter, "{} (code {})", self.message, self.code.code())
+    }
+}
+
+/// Reasons the login callback could not be received
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoginError {
+    /// The user did not grant access in time
+    Timeout,
+    /// The login was cancelled before the user granted access
+    Cancelled,
+    /// The callback was for another request token than the one issued for this login
+    TokenMismatch,
+    /// The callback did not carry a verifier
+    MissingVerifier,
+    /// The callback server stopped before receiving an answer
+    ServerClosed,
+}
+
+impl Display for LoginError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let description = match self {
+            LoginError::Timeout => "timed out waiting for the user to grant access",
+            LoginError::Cancelled => "login cancelled",
+            LoginError::TokenMismatch => "callback received for an unknown request token",
+            LoginError::MissingVerifier => "callback received without a verifier",
+            LoginError::ServerClosed => "callback server stopped",
+        };
+
+        write!(formatter, "{description}")
+    }
+}
+
+/// Error type returned by every method of the crate
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or its answer could not be received
+    Transport(reqwest::Error),
+    /// The server answered with an unexpected HTTP status
+    Http { status: u16, body: String },
+    /// The answer could not be decoded from JSON
+    Json {
+        source: serde_json::Error,
+        body: String,
+    },
+    /// The answer could not be decoded from XML
+    Xml {
+        source: serde_xml_rs::Error,
+        body: String,
+    },
+    /// The answer of an OAuth endpoint could not be decoded
+    UrlEncoded {
+        source: serde_urlencoded::de::Error,
+        body: String,
+    },
+    /// An OAuth endpoint refused the request
+    Oauth(OauthErrorDescription),
+    /// The API answered with an error
+    Api(FlickrError),
+    /// An URL could not be built from the parameters
+    Url(url::ParseError),
+    /// A local IO operation failed, or a [Transport](crate::Transport) reported a network
+    /// failure
+    Io(std::io::Error),
+    /// The login callback could not be received
+    Login(LoginError),
+    /// The token was not granted the access level the method needs
+    InsufficientPermission {
+        required: Permission,
+        granted: Permission,
+    },
+}
+
+impl Error {
+    /// The flickr error code, if the API answered with an error
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::Api(e) => Some(e.code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Error::Transport(e) => write!(formatter, "transport error: {e}"),
+            Error::Http { status, .. } => write!(formatter, "unexpected HTTP status {status}"),
+            Error::Json { source, .. } => write!(formatter, "failed to decode JSON: {source}"),
+            Error::Xml { source, .. } => write!(formatter, "failed to decode XML: {source}"),
+            Error::UrlEncoded { source, .. } => {
+                write!(formatter, "failed to decode OAuth answer: {source}")
+            }
+            Error::Oauth(e) => write!(formatter, "OAuth problem: {}", e.oauth_problem),
+            Error::Api(e) => write!(formatter, "{e}"),
+            Error::Url(e) => write!(formatter, "invalid URL: {e}"),
+            Error::Io(e) => write!(formatter, "{e}"),
+            Error::Login(e) => write!(formatter, "login failed: {e}"),
+            Error::InsufficientPermission { required, granted } => write!(
+                formatter,
+                "method requires {required} permission, token was granted {granted}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Json { source, .. } => Some(source),
+            Error::Xml { source, .. } => Some(source),
+            Error::UrlEncoded { source, .. } => Some(source),
+            Error::Api(e) => Some(e),
+            Error::Url(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Http { .. }
+            | Error::Oauth(_)
+            | Error::Login(_)
+            | Error::InsufficientPermission { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(e: url::ParseError) -> Self {
+        Error::Url(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<FlickrError> for Error {
+    fn from(e: FlickrError) -> Self {
+        Error::Api(e)
+    }
+}
+
+impl From<LoginError> for Error {
+    fn from(e: LoginError) -> Self {
+        Error::Login(e)
+    }
+}
+
+impl From<OauthErrorDescription> for Error {
+    fn from(e: OauthErrorDescription) -> Self {
+        Error::Oauth(e)
+    }
+}
+
+#[test]
+fn test_error_code_roundtrip() {
+    for code in [1, 95, 98, 99, 105, 112, 116, 200] {
+        assert_eq!(ErrorCode::from(code).code(), code);
+    }
+
+    assert_eq!(ErrorCode::from(1), ErrorCode::MethodSpecific(1));
+    assert_eq!(ErrorCode::from(98), ErrorCode::InvalidAuthToken);
+    assert_eq!(ErrorCode::from(200), ErrorCode::Unknown(200));
+}
+
+#[test]
+fn test_flickr_error_serialization() {
+    let error: FlickrError = serde_json::from_str(
+        r#"{"stat":"fail","code":105,"message":"Service currently unavailable"}"#,
+    )
+    .unwrap();
+    assert_eq!(error.code, ErrorCode::ServiceUnavailable);
+
+    let serialized = serde_json::to_string(&error).unwrap();
+    assert_eq!(
+        serialized,
+        r#"{"stat":"fail","code":105,"message":"Service currently unavailable"}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<FlickrError>(&serialized).unwrap(),
+        error
+    );
+}
+
+#[test]
+fn test_oauth_error_serialization() {
+    let problem: OauthErrorDescription =
+        serde_urlencoded::from_str("oauth_problem=signature_invalid&debug_sbs=GET%26http").unwrap();
+
+    let serialized = serde_json::to_value(&problem).unwrap();
+    assert_eq!(serialized["oauth_problem"], "signature_invalid");
+    assert_eq!(serialized["debug_sbs"], "GET&http");
+    assert_eq!(
+        serde_json::from_value::<OauthErrorDescription>(serialized).unwrap(),
+        problem
+    );
+}
@@ -0,0 +1,83 @@
+use crate::*;
+
+#[derive(Deserialize, Debug)]
+struct GetAccessTokenAnswerSuccess {
+    auth: AccessTokenWrapper,
+}
+
+#[derive(Deserialize, Debug)]
+struct AccessTokenWrapper {
+    access_token: AccessToken,
+}
+
+#[derive(Deserialize, Debug)]
+struct AccessToken {
+    oauth_token: String,
+    oauth_token_secret: String,
+}
+
+impl AuthRequestBuilder {
+    /// [flickr.auth.oauth.getAccessToken](https://www.flickr.com/services/api/flickr.auth.oauth.getAccessToken.html)
+    /// endpoint. Exchanges a token from the legacy authentication API for an OAuth token.
+    ///
+    /// Flickr deletes the legacy token once exchanged. Use a client without a token to call this
+    /// method, then [FlickrAPI::with_token] to use the new one.
+    pub async fn get_access_token(&self, legacy_token: &str) -> Result<OauthToken, Error> {
+        let answer: GetAccessTokenAnswerSuccess = self
+            .handle
+            .call_as(
+                "flickr.auth.oauth.getAccessToken",
+                vec![("auth_token", legacy_token.to_string())],
+            )
+            .await?;
+
+        Ok(OauthToken {
+            token: answer.auth.access_token.oauth_token,
+            secret: answer.auth.access_token.oauth_token_secret,
+            ..Default::default()
+        })
+    }
+}
+
+#[test]
+fn test_get_access_token_answer() {
+    let raw = r#"{"auth":{"access_token":{"oauth_token":"72157607082540144-8d5d7ea7696629bf","oauth_token_secret":"f38bf58b2d95bc8b"}},"stat":"ok"}"#;
+
+    let answer: GetAccessTokenAnswerSuccess = serde_json::from_str(raw).unwrap();
+
+    assert_eq!(
+        answer.auth.access_token.oauth_token,
+        "72157607082540144-8d5d7ea7696629bf"
+    );
+    assert_eq!(
+        answer.auth.access_token.oauth_token_secret,
+        "f38bf58b2d95bc8b"
+    );
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_get_access_token() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let legacy = server.issue_legacy_token(Permission::Write);
+
+    let token = server
+        .client()
+        .auth()
+        .get_access_token(&legacy)
+        .await
+        .unwrap();
+    let client = server.client().with_token(token);
+    assert_eq!(
+        client.auth().check_token().await.unwrap().perms,
+        Permission::Write
+    );
+
+    // Legacy tokens can only be exchanged once
+    match server.client().auth().get_access_token(&legacy).await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::InvalidAuthToken),
+        other => panic!("Unexpected answer: {:?}", other.map(|_| ())),
+    }
+}
@@ -1,43 +1,49 @@
 #![allow(dead_code)]
 use crate::*;
+use serde::Serializer;
+use std::time::SystemTime;
 
-#[derive(Deserialize, Debug, Hash)]
-#[serde(untagged)]
-enum FlickrGetInfoAnswer {
-    Ok(FlickrGetInfoSuccess),
-    Err(FlickrError),
-}
-
-impl Resultable<PhotoInfo, Box<dyn Error>> for FlickrGetInfoAnswer {
-    fn to_result(self) -> Result<PhotoInfo, Box<dyn Error>> {
-        match self {
-            FlickrGetInfoAnswer::Ok(info) => Ok(info.photo),
-            FlickrGetInfoAnswer::Err(e) => Err(Box::new(e)),
-        }
-    }
-}
-
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Deserialize, Debug)]
 struct FlickrGetInfoSuccess {
     stat: String,
     photo: PhotoInfo,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+/// Information about a photo, as returned by flickr.photos.getInfo
+///
+/// Serializes to a stable JSON form, which deserializes back to the same value:
+/// - the `{"_content": ...}` wrappers flickr uses are flattened, so `title` is a string and
+///   `comments` a number
+/// - dates are seconds since the Unix epoch, except `taken` which is `YYYY-MM-DD HH:MM:SS`
+/// - [License], [SafetyLevel] and [TakenGranularity] are their numeric flickr IDs
+/// - flags are booleans and missing optional data is `null`
+/// - the text of notes and tags is `text`, and the address of URLs is `url`
+///
+/// The only exception is `exif`, kept as sent by flickr.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PhotoInfo {
-    pub dateuploaded: String,
+    #[serde(with = "de::timestamp")]
+    pub dateuploaded: SystemTime,
+    #[serde(deserialize_with = "de::u32")]
     pub farm: u32,
-    pub id: String,
-    pub isfavorite: u32,
-    pub license: String,
-    pub originalformat: String,
-    pub originalsecret: String,
+    pub id: PhotoId,
+    #[serde(deserialize_with = "de::bool")]
+    pub isfavorite: bool,
+    pub license: License,
+    /// Only sent when the caller may download the original
+    pub originalformat: Option<String>,
+    /// Only sent when the caller may download the original
+    pub originalsecret: Option<Secret>,
+    #[serde(deserialize_with = "de::u32")]
     pub rotation: u32,
-    pub safety_level: String,
-    pub secret: String,
+    pub safety_level: SafetyLevel,
+    pub secret: Secret,
     pub server: String,
-    pub views: String,
-    pub media: String,
+    #[serde(default, deserialize_with = "de::u64")]
+    pub views: u64,
+    pub media: Media,
+    /// Sent for videos
+    pub video: Option<Video>,
 
     pub owner: Owner,
     pub dates: Dates,
@@ -48,143 +54,528 @@ pub struct PhotoInfo {
     #[serde(deserialize_with = "deserialize_content")]
     pub description: String,
 
-    #[serde(deserialize_with = "deserialize_content")]
-    pub comments: String,
+    /// Number of comments
+    #[serde(deserialize_with = "deserialize_content_count")]
+    pub comments: u64,
 
-    pub permissions: Permissions,
-    pub editability: Editability,
-    pub publiceditability: Editability,
+    pub visibility: Option<Visibility>,
+    /// Only sent to the owner
+    pub permissions: Option<Permissions>,
+    pub editability: Option<Editability>,
+    pub publiceditability: Option<Editability>,
 
-    // Either "" or a whole object
-    pub location: Location,
+    /// Missing or empty when the photo is not geotagged or its location is hidden
+    #[serde(default, deserialize_with = "deserialize_location")]
+    pub location: Option<LocationData>,
 
-    pub geoperms: GeoPerms,
+    /// Only sent to the owner of a geotagged photo
+    pub geoperms: Option<GeoPerms>,
 
+    #[serde(default)]
     pub notes: NoteWrapper,
+    pub people: Option<People>,
+    #[serde(default)]
     pub tags: TagWrapper,
+    #[serde(default)]
     pub urls: UrlWrapper,
-    pub usage: Usage,
+    pub usage: Option<Usage>,
+
+    /// EXIF hints, sent with some photos. Their layout is not documented so they are kept, and
+    /// serialized, exactly as flickr sent them. This field is not covered by the stable form, use
+    /// [flickr.photos.getExif](https://www.flickr.com/services/api/flickr.photos.getExif.html) for
+    /// structured data
+    pub exif: Option<Value>,
+}
+
+/// The location is sent as `""`, `null` or `{}` when the photo is not geotagged
+fn deserialize_location<'de, D>(deserializer: D) -> Result<Option<LocationData>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+
+    match &value {
+        Value::Null => Ok(None),
+        Value::String(text) if text.is_empty() => Ok(None),
+        Value::Object(fields) if fields.is_empty() => Ok(None),
+        _ => LocationData::deserialize(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Number sent as `{"_content": "12"}` by flickr and as `12` by this crate
+fn deserialize_content_count<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Content {
+        #[serde(deserialize_with = "de::u64")]
+        _content: u64,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Count {
+        Wrapped(Content),
+        Plain(#[serde(deserialize_with = "de::u64")] u64),
+    }
+
+    Ok(match Count::deserialize(deserializer)? {
+        Count::Wrapped(content) => content._content,
+        Count::Plain(count) => count,
+    })
+}
+
+/// [License](https://www.flickr.com/services/api/flickr.photos.licenses.getInfo.html) of a photo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum License {
+    AllRightsReserved,
+    CcByNcSa2,
+    CcByNc2,
+    CcByNcNd2,
+    CcBy2,
+    CcBySa2,
+    CcByNd2,
+    NoKnownCopyrightRestrictions,
+    UsGovernmentWork,
+    Cc0,
+    PublicDomainMark,
+    CcBy4,
+    CcBySa4,
+    CcByNd4,
+    CcByNc4,
+    CcByNcSa4,
+    CcByNcNd4,
+    /// A license this crate does not know about
+    Other(u32),
+}
+
+impl License {
+    /// The ID flickr uses for this license
+    pub fn id(&self) -> u32 {
+        match self {
+            License::AllRightsReserved => 0,
+            License::CcByNcSa2 => 1,
+            License::CcByNc2 => 2,
+            License::CcByNcNd2 => 3,
+            License::CcBy2 => 4,
+            License::CcBySa2 => 5,
+            License::CcByNd2 => 6,
+            License::NoKnownCopyrightRestrictions => 7,
+            License::UsGovernmentWork => 8,
+            License::Cc0 => 9,
+            License::PublicDomainMark => 10,
+            License::CcBy4 => 11,
+            License::CcBySa4 => 12,
+            License::CcByNd4 => 13,
+            License::CcByNc4 => 14,
+            License::CcByNcSa4 => 15,
+            License::CcByNcNd4 => 16,
+            License::Other(id) => *id,
+        }
+    }
+}
+
+impl From<u32> for License {
+    fn from(id: u32) -> Self {
+        match id {
+            0 => License::AllRightsReserved,
+            1 => License::CcByNcSa2,
+            2 => License::CcByNc2,
+            3 => License::CcByNcNd2,
+            4 => License::CcBy2,
+            5 => License::CcBySa2,
+            6 => License::CcByNd2,
+            7 => License::NoKnownCopyrightRestrictions,
+            8 => License::UsGovernmentWork,
+            9 => License::Cc0,
+            10 => License::PublicDomainMark,
+            11 => License::CcBy4,
+            12 => License::CcBySa4,
+            13 => License::CcByNd4,
+            14 => License::CcByNc4,
+            15 => License::CcByNcSa4,
+            16 => License::CcByNcNd4,
+            id => License::Other(id),
+        }
+    }
+}
+
+impl Serialize for License {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.id())
+    }
 }
 
-#[derive(Deserialize, Debug, Hash)]
+impl<'de> Deserialize<'de> for License {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        de::u32(deserializer).map(License::from)
+    }
+}
+
+/// Who a photo is suitable for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SafetyLevel {
+    Safe,
+    Moderate,
+    Restricted,
+    /// A level this crate does not know about
+    Other(u32),
+}
+
+impl SafetyLevel {
+    /// The value flickr uses for this level
+    pub fn id(&self) -> u32 {
+        match self {
+            SafetyLevel::Safe => 0,
+            SafetyLevel::Moderate => 1,
+            SafetyLevel::Restricted => 2,
+            SafetyLevel::Other(id) => *id,
+        }
+    }
+}
+
+impl From<u32> for SafetyLevel {
+    fn from(id: u32) -> Self {
+        match id {
+            0 => SafetyLevel::Safe,
+            1 => SafetyLevel::Moderate,
+            2 => SafetyLevel::Restricted,
+            id => SafetyLevel::Other(id),
+        }
+    }
+}
+
+impl Serialize for SafetyLevel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for SafetyLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        de::u32(deserializer).map(SafetyLevel::from)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Media {
+    Photo,
+    Video,
+    /// A media type this crate does not know about
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Owner {
-    pub nsid: String,
+    pub nsid: UserId,
     pub username: String,
+    #[serde(default)]
     pub realname: String,
-    pub location: String,
+    pub location: Option<String>,
     pub iconserver: String,
+    #[serde(default, deserialize_with = "de::u32")]
     pub iconfarm: u32,
     pub path_alias: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Dates {
-    pub posted: String,
-    pub taken: String,
-    pub takengranularity: u32,
-    pub takenunknown: String,
-    pub lastupdate: String,
+    #[serde(with = "de::timestamp")]
+    pub posted: SystemTime,
+    /// When the photo was taken, in the photographer's local time, only as precise as
+    /// `takengranularity`
+    pub taken: DateTaken,
+    pub takengranularity: TakenGranularity,
+    /// The date taken was not known and was set to the upload date
+    #[serde(default, deserialize_with = "de::bool")]
+    pub takenunknown: bool,
+    #[serde(with = "de::timestamp")]
+    pub lastupdate: SystemTime,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+/// A date and time without time zone, as sent by flickr: `2024-01-31 18:05:00`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTaken {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl std::str::FromStr for DateTaken {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date: {value:?}");
+        let numbers = value
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<u16>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let field = |index: usize| numbers.get(index).copied().unwrap_or_default();
+        let small = |index: usize| u8::try_from(field(index)).map_err(|_| invalid());
+
+        if numbers.is_empty() || numbers.len() > 6 {
+            return Err(invalid());
+        }
+
+        Ok(DateTaken {
+            year: field(0),
+            month: small(1)?,
+            day: small(2)?,
+            hour: small(3)?,
+            minute: small(4)?,
+            second: small(5)?,
+        })
+    }
+}
+
+impl std::fmt::Display for DateTaken {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl Serialize for DateTaken {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTaken {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// How precise the date a photo was taken is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TakenGranularity {
+    Second,
+    Month,
+    Year,
+    /// Around the year given
+    Circa,
+    /// A granularity this crate does not know about
+    Other(u32),
+}
+
+impl TakenGranularity {
+    /// The value flickr uses for this granularity
+    pub fn id(&self) -> u32 {
+        match self {
+            TakenGranularity::Second => 0,
+            TakenGranularity::Month => 4,
+            TakenGranularity::Year => 6,
+            TakenGranularity::Circa => 8,
+            TakenGranularity::Other(id) => *id,
+        }
+    }
+}
+
+impl From<u32> for TakenGranularity {
+    fn from(id: u32) -> Self {
+        match id {
+            0 => TakenGranularity::Second,
+            4 => TakenGranularity::Month,
+            6 => TakenGranularity::Year,
+            8 => TakenGranularity::Circa,
+            id => TakenGranularity::Other(id),
+        }
+    }
+}
+
+impl Serialize for TakenGranularity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for TakenGranularity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        de::u32(deserializer).map(TakenGranularity::from)
+    }
+}
+
+/// Who can comment or add notes and tags: `0` nobody, `1` friends and family, `2` contacts,
+/// `3` everybody
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Permissions {
+    #[serde(deserialize_with = "de::u32")]
     pub permcomment: u32,
+    #[serde(deserialize_with = "de::u32")]
     pub permaddmeta: u32,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Editability {
-    pub cancomment: u32,
-    pub canaddmeta: u32,
+    #[serde(deserialize_with = "de::bool")]
+    pub cancomment: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub canaddmeta: bool,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Usage {
-    pub candownload: u32,
-    pub canblog: u32,
-    pub canprint: u32,
-    pub canshare: u32,
+    #[serde(deserialize_with = "de::bool")]
+    pub candownload: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub canblog: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub canprint: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub canshare: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Visibility {
+    #[serde(deserialize_with = "de::bool")]
+    pub ispublic: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub isfriend: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub isfamily: bool,
 }
 
-#[derive(Deserialize, Debug, Hash)]
-#[serde(untagged)]
-pub enum Location {
-    Full(LocationData),
-    Empty(String),
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct People {
+    /// People are tagged in the photo
+    #[serde(deserialize_with = "de::bool")]
+    pub haspeople: bool,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+/// Encoding status and dimensions of a video
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Video {
+    #[serde(deserialize_with = "de::bool")]
+    pub ready: bool,
+    #[serde(default, deserialize_with = "de::bool")]
+    pub failed: bool,
+    #[serde(default, deserialize_with = "de::bool")]
+    pub pending: bool,
+    /// In seconds
+    #[serde(default, deserialize_with = "de::u32")]
+    pub duration: u32,
+    #[serde(default, deserialize_with = "de::u32")]
+    pub width: u32,
+    #[serde(default, deserialize_with = "de::u32")]
+    pub height: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LocationData {
-    pub latitude: String,
-    pub longitude: String,
-    pub accuracy: String,
-    pub context: String,
-    #[serde(deserialize_with = "deserialize_content")]
+    #[serde(deserialize_with = "de::f64")]
+    pub latitude: f64,
+    #[serde(deserialize_with = "de::f64")]
+    pub longitude: f64,
+    /// From `1`, world level, to `16`, street level
+    #[serde(deserialize_with = "de::u32")]
+    pub accuracy: u32,
+    /// `0` not defined, `1` indoors, `2` outdoors
+    #[serde(default, deserialize_with = "de::u32")]
+    pub context: u32,
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub locality: String,
-    #[serde(deserialize_with = "deserialize_content")]
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub county: String,
-    #[serde(deserialize_with = "deserialize_content")]
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub region: String,
-    #[serde(deserialize_with = "deserialize_content")]
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub country: String,
-    #[serde(deserialize_with = "deserialize_content")]
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub neighbourhood: String,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GeoPerms {
-    pub ispublic: u32,
-    pub iscontact: u32,
-    pub isfriend: u32,
-    pub isfamily: u32,
+    #[serde(deserialize_with = "de::bool")]
+    pub ispublic: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub iscontact: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub isfriend: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub isfamily: bool,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct NoteWrapper {
+    #[serde(default)]
     pub note: Vec<Note>,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+/// A note on a photo. Its position and size are in pixels, on the 500px wide version of the
+/// photo
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Note {
     pub id: String,
-    pub photo_id: String,
-    pub author: String,
+    pub photo_id: PhotoId,
+    pub author: UserId,
     pub authorname: String,
     pub authorrealname: String,
-    pub authorispro: u32,
-    pub authorisdeleted: u32,
-    pub x: String,
-    pub y: String,
-    pub w: String,
-    pub h: String,
-    pub _content: String,
+    #[serde(deserialize_with = "de::bool")]
+    pub authorispro: bool,
+    #[serde(deserialize_with = "de::bool")]
+    pub authorisdeleted: bool,
+    #[serde(deserialize_with = "de::u32")]
+    pub x: u32,
+    #[serde(deserialize_with = "de::u32")]
+    pub y: u32,
+    #[serde(deserialize_with = "de::u32")]
+    pub w: u32,
+    #[serde(deserialize_with = "de::u32")]
+    pub h: u32,
+    /// Text of the note
+    #[serde(alias = "_content")]
+    pub text: String,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct TagWrapper {
+    #[serde(default)]
     pub tag: Vec<Tag>,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Tag {
     pub id: String,
-    pub author: String,
+    pub author: UserId,
     pub authorname: String,
+    /// The tag as entered
     pub raw: String,
-    pub _content: String,
-    pub machine_tag: u32,
+    /// The tag normalized by flickr: lowercase, without spaces or punctuation
+    #[serde(alias = "_content")]
+    pub text: String,
+    #[serde(deserialize_with = "de::bool")]
+    pub machine_tag: bool,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct UrlWrapper {
+    #[serde(default)]
     pub url: Vec<Url>,
 }
 
-#[derive(Deserialize, Debug, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Url {
     #[serde(rename = "type")]
     pub urltype: String,
-    pub _content: String,
+    #[serde(alias = "_content")]
+    pub url: String,
 }
 
 impl PhotoRequestBuilder {
@@ -195,33 +586,249 @@ impl PhotoRequestBuilder {
     /// will authenticate the user if given the token.
     pub async fn get_info(
         &self,
-        id: &String,
-        secret: Option<&String>,
-    ) -> Result<PhotoInfo, Box<dyn Error>> {
-        let mut params = vec![
-            ("method", "flickr.photos.getInfo".into()),
-            ("photo_id", id.clone()),
-            ("nojsoncallback", "1".into()),
-            ("format", "json".into()),
-            ("api_key", self.handle.key.key.clone()),
-        ];
+        id: impl Into<PhotoId>,
+        secret: Option<Secret>,
+    ) -> Result<PhotoInfo, Error> {
+        let mut params = vec![("photo_id", id.into().into_string())];
         if let Some(value) = secret {
-            params.push(("secret", value.clone()));
+            params.push(("secret", value.into_string()));
         }
-        oauth::build_request(
-            oauth::RequestTarget::Get(URL_API),
-            &mut params,
-            &self.handle.key,
-            self.handle.token.as_ref(),
+
+        let answer: FlickrGetInfoSuccess =
+            self.handle.call_as("flickr.photos.getInfo", params).await?;
+
+        Ok(answer.photo)
+    }
+}
+
+#[test]
+fn test_typed_photo_info() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // Flickr sends the same kind of value either as a number or as a string
+    let answer: FlickrGetInfoSuccess = serde_json::from_str(
+        r#"{"photo": {
+            "id": "54000000001", "secret": "abcdef", "server": "65535", "farm": "66",
+            "dateuploaded": "1700000000", "isfavorite": 1, "license": "4", "safety_level": 1,
+            "rotation": "90", "originalsecret": "123456", "originalformat": "jpg",
+            "owner": {"nsid": "12345678@N00", "username": "user", "realname": "", "location": "",
+                "iconserver": "0", "iconfarm": "0"},
+            "title": {"_content": "Dog"}, "description": {"_content": ""},
+            "dates": {"posted": 1700000000, "taken": "2023-11-14 22:13:20",
+                "takengranularity": "6", "takenunknown": 0, "lastupdate": "1700000100"},
+            "views": "1234", "permissions": {"permcomment": "3", "permaddmeta": 2},
+            "editability": {"cancomment": "1", "canaddmeta": 0},
+            "publiceditability": {"cancomment": 1, "canaddmeta": "0"},
+            "usage": {"candownload": 1, "canblog": "0", "canprint": 0, "canshare": "1"},
+            "comments": {"_content": 3},
+            "location": {"latitude": "48.858370", "longitude": 2.294481, "accuracy": "16",
+                "context": 2, "locality": {"_content": "Paris"}, "county": {"_content": ""},
+                "region": {"_content": ""}, "country": {"_content": "France"},
+                "neighbourhood": {"_content": ""}},
+            "geoperms": {"ispublic": "1", "iscontact": 0, "isfriend": 0, "isfamily": 0},
+            "notes": {"note": [{"id": "1", "photo_id": "54000000001", "author": "12345678@N00",
+                "authorname": "user", "authorrealname": "", "authorispro": "0",
+                "authorisdeleted": 0, "x": "10", "y": 20, "w": "30", "h": "40", "_content": "Nose"}]},
+            "tags": {"tag": [{"id": "1", "author": "12345678@N00", "authorname": "user",
+                "raw": "geo:lat=48.85", "_content": "geolat4885", "machine_tag": 1}]},
+            "urls": {"url": []},
+            "media": "video"
+        }, "stat": "ok"}"#,
+    )
+    .unwrap();
+    let photo = answer.photo;
+
+    assert_eq!(photo.farm, 66);
+    assert_eq!(
+        photo.dateuploaded,
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+    assert!(photo.isfavorite);
+    assert_eq!(photo.license, License::CcBy2);
+    assert_eq!(photo.license.id(), 4);
+    assert_eq!(photo.safety_level, SafetyLevel::Moderate);
+    assert_eq!(photo.rotation, 90);
+    assert_eq!(photo.views, 1234);
+    assert_eq!(photo.comments, 3);
+    assert_eq!(photo.media, Media::Video);
+
+    assert_eq!(photo.dates.taken.to_string(), "2023-11-14 22:13:20");
+    assert_eq!(photo.dates.taken.year, 2023);
+    assert_eq!(photo.dates.takengranularity, TakenGranularity::Year);
+    assert!(!photo.dates.takenunknown);
+    assert_eq!(
+        photo.dates.lastupdate,
+        UNIX_EPOCH + Duration::from_secs(1_700_000_100)
+    );
+
+    assert_eq!(photo.permissions.unwrap().permcomment, 3);
+    let editability = photo.editability.unwrap();
+    assert!(editability.cancomment && !editability.canaddmeta);
+    let usage = photo.usage.unwrap();
+    assert!(usage.canshare && !usage.canblog);
+    assert!(photo.geoperms.unwrap().ispublic);
+
+    let location = photo.location.unwrap();
+    assert_eq!(location.latitude, 48.85837);
+    assert_eq!(location.longitude, 2.294481);
+    assert_eq!(location.accuracy, 16);
+    assert_eq!(location.locality, "Paris");
+
+    let note = &photo.notes.note[0];
+    assert_eq!((note.x, note.y, note.w, note.h), (10, 20, 30, 40));
+    assert!(!note.authorispro);
+    assert!(photo.tags.tag[0].machine_tag);
+
+    assert_eq!(License::from(42), License::Other(42));
+    assert_eq!(
+        "2023-11".parse(),
+        Ok(DateTaken {
+            year: 2023,
+            month: 11,
+            ..Default::default()
+        })
+    );
+    assert!("yesterday".parse::<DateTaken>().is_err());
+}
+
+#[test]
+fn test_photo_info_fixtures() {
+    let fixture = |name: &str| -> PhotoInfo {
+        let path = format!(
+            "{}/tests/fixtures/get_info/{name}",
+            env!("CARGO_MANIFEST_DIR")
         );
+        let contents = std::fs::read_to_string(&path).unwrap();
 
-        let url = reqwest::Url::parse_with_params(URL_API, &params)?;
-        let fetch = self.handle.client.get(url).send().await?;
-        let raw = fetch.text().await?;
-        #[cfg(debug_assertions)]
-        log::debug!("Received {raw}");
-        let answer: FlickrGetInfoAnswer = serde_json::from_str(&raw)?;
+        match serde_json::from_str::<FlickrGetInfoSuccess>(&contents) {
+            Ok(answer) => answer.photo,
+            Err(e) => panic!("Failed to parse {name}: {e}"),
+        }
+    };
 
-        answer.to_result()
+    let fixtures = std::fs::read_dir(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/get_info"
+    ))
+    .unwrap()
+    .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+    .filter(|name| name.ends_with(".json"))
+    .collect::<Vec<_>>();
+    assert!(fixtures.len() >= 4);
+    for name in fixtures {
+        fixture(&name);
     }
+
+    // Someone else's photo, without access to the original
+    let public = fixture("public_photo.json");
+    assert!(public.originalsecret.is_none() && public.originalformat.is_none());
+    assert!(public.permissions.is_none() && public.geoperms.is_none());
+    assert_eq!(public.owner.path_alias.as_deref(), Some("lighthousekeeper"));
+    assert_eq!(public.location.unwrap().county, "Finistère");
+    assert!(public.visibility.unwrap().ispublic);
+    assert!(!public.usage.unwrap().candownload);
+
+    let own = fixture("own_photo.json");
+    assert_eq!(own.originalformat.as_deref(), Some("jpg"));
+    assert_eq!(own.license, License::Cc0);
+    assert_eq!(own.location.unwrap().neighbourhood, "Croix-Rousse");
+    assert!(own.geoperms.unwrap().isfamily);
+    assert!(own.people.unwrap().haspeople);
+    assert_eq!(own.notes.note[0].x, 212);
+
+    let video = fixture("video.json");
+    assert_eq!(video.media, Media::Video);
+    assert!(video.originalsecret.is_none() && video.originalformat.is_none());
+    let details = video.video.unwrap();
+    assert!(details.ready);
+    assert_eq!(
+        (details.duration, details.width, details.height),
+        (23, 1920, 1080)
+    );
+
+    // Restricted photo of an old account, without location nor access to the original
+    let restricted = fixture("restricted_photo.json");
+    assert_eq!(restricted.safety_level, SafetyLevel::Restricted);
+    assert!(restricted.location.is_none() && restricted.originalformat.is_none());
+    assert!(restricted.owner.path_alias.is_none());
+    assert!(restricted.notes.note.is_empty() && restricted.tags.tag.is_empty());
+    assert_eq!(restricted.views, 0);
+    assert_eq!(restricted.dates.takengranularity, TakenGranularity::Month);
+    assert_eq!(restricted.dates.taken.to_string(), "2008-12-01 00:00:00");
+}
+
+#[test]
+fn test_photo_info_round_trip() {
+    let directory = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/get_info");
+
+    for entry in std::fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let photo = serde_json::from_str::<FlickrGetInfoSuccess>(&contents)
+            .unwrap()
+            .photo;
+
+        let serialized = serde_json::to_value(&photo).unwrap();
+        assert_eq!(photo, serde_json::from_value(serialized.clone()).unwrap());
+
+        assert!(serialized["title"].is_string());
+        assert!(serialized["comments"].is_u64());
+        assert!(serialized["dateuploaded"].is_u64());
+        assert!(serialized["license"].is_u64());
+        assert!(serialized["isfavorite"].is_boolean());
+    }
+
+    let own = serde_json::from_str::<FlickrGetInfoSuccess>(include_str!(
+        "../tests/fixtures/get_info/own_photo.json"
+    ))
+    .unwrap()
+    .photo;
+    let serialized = serde_json::to_value(&own).unwrap();
+
+    assert_eq!(serialized["dateuploaded"], 1686748800);
+    assert_eq!(serialized["license"], 9);
+    assert_eq!(serialized["dates"]["taken"], "2023-06-14 15:20:00");
+    assert_eq!(serialized["location"]["locality"], "Lyon");
+    assert_eq!(serialized["video"], Value::Null);
+    assert_eq!(serialized["notes"]["note"][0]["text"], "the cat");
+    assert_eq!(
+        serialized["tags"]["tag"][0]["text"],
+        "upload:source=flickrapi"
+    );
+    assert!(serialized["urls"]["url"][0]["url"].is_string());
+    assert!(!serialized.to_string().contains("_content"));
+}
+
+#[test]
+fn test_photo_info_location() {
+    let fixture = include_str!("../tests/fixtures/get_info/restricted_photo.json");
+    let with_location = |location: &str| {
+        serde_json::from_str::<FlickrGetInfoSuccess>(&fixture.replace(
+            r#""media":"photo""#,
+            &format!(r#""location":{location},"media":"photo""#),
+        ))
+        .map(|answer| answer.photo.location)
+    };
+
+    for empty in [r#""""#, "null", "{}"] {
+        assert_eq!(with_location(empty).unwrap(), None);
+    }
+    assert_eq!(
+        with_location(r#"{"latitude":"45.5","longitude":"4.8","accuracy":"11"}"#)
+            .unwrap()
+            .map(|location| location.accuracy),
+        Some(11)
+    );
+
+    // A broken location is an error, not a missing one
+    let error = with_location(r#"{"latitude":"north","longitude":"4.8","accuracy":"11"}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("invalid number"), "{error}");
+    assert!(with_location(r#""somewhere""#).is_err());
+    assert!(with_location(r#"{"longitude":"4.8"}"#).is_err());
 }
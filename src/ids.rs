@@ -0,0 +1,210 @@
+use crate::*;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A string that could not be parsed as an identifier
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseIdError {
+    /// The kind of identifier expected, e.g. `photo ID`
+    pub kind: &'static str,
+    pub value: String,
+}
+
+impl Display for ParseIdError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Invalid {}: {:?}", self.kind, self.value)
+    }
+}
+
+impl std::error::Error for ParseIdError {}
+
+fn is_number(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `12345678@N00`
+fn is_nsid(value: &str) -> bool {
+    value
+        .split_once("@N")
+        .is_some_and(|(user, suffix)| is_number(user) && is_number(suffix))
+}
+
+/// `72157617483228192` or `6065-72157617483228192`
+fn is_gallery(value: &str) -> bool {
+    match value.split_once('-') {
+        Some((user, gallery)) => is_number(user) && is_number(gallery),
+        None => is_number(value),
+    }
+}
+
+fn is_secret(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Declare a newtype around the string flickr uses as an identifier
+///
+/// Conversions from strings are unchecked so existing IDs can be passed around freely, parsing
+/// with [FromStr] checks the format.
+macro_rules! identifier {
+    ($(#[$doc:meta])* $name:ident, $kind:literal, $valid:expr) => {
+        $(#[$doc])*
+        #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(value: impl Into<String>) -> Self {
+                $name(value.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIdError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                match $valid(value) {
+                    true => Ok($name(value.to_string())),
+                    false => Err(ParseIdError {
+                        kind: $kind,
+                        value: value.to_string(),
+                    }),
+                }
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                &self.0 == other
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(value: &String) -> Self {
+                $name(value.clone())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                $name(value.to_string())
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(value: &$name) -> Self {
+                value.clone()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+identifier!(
+    /// A photo or video, e.g. `54000000001`
+    PhotoId,
+    "photo ID",
+    is_number
+);
+
+identifier!(
+    /// A user, also called NSID, e.g. `12345678@N00`
+    UserId,
+    "user ID",
+    is_nsid
+);
+
+identifier!(
+    /// An album, e.g. `72157626216528324`
+    PhotosetId,
+    "photoset ID",
+    is_number
+);
+
+identifier!(
+    /// A gallery, e.g. `6065-72157617483228192`
+    GalleryId,
+    "gallery ID",
+    is_gallery
+);
+
+identifier!(
+    /// A group, e.g. `34427469792@N01`
+    GroupId,
+    "group ID",
+    is_nsid
+);
+
+identifier!(
+    /// The secret of a photo, used in its URLs and to bypass permission checks, e.g. `a1b2c3d4e5`
+    Secret,
+    "secret",
+    is_secret
+);
+
+#[test]
+fn test_parse_ids() {
+    assert_eq!("54000000001".parse::<PhotoId>().unwrap(), "54000000001");
+    assert_eq!(
+        "12345678@N00".parse::<UserId>(),
+        Ok(UserId::new("12345678@N00"))
+    );
+    assert!("6065-72157617483228192".parse::<GalleryId>().is_ok());
+    assert!("72157617483228192".parse::<GalleryId>().is_ok());
+    assert!("0f9e8d7c6b".parse::<Secret>().is_ok());
+
+    // A user ID is not a photo ID
+    match "12345678@N00".parse::<PhotoId>() {
+        Err(e) => assert_eq!(e.to_string(), r#"Invalid photo ID: "12345678@N00""#),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+    assert!("54000000001".parse::<GroupId>().is_err());
+    assert!("".parse::<PhotosetId>().is_err());
+    assert!("12-".parse::<GalleryId>().is_err());
+
+    let id: PhotoId = serde_json::from_str(r#""54000000001""#).unwrap();
+    assert_eq!(serde_json::to_string(&id).unwrap(), r#""54000000001""#);
+    assert_eq!(id.to_string(), "54000000001");
+}
@@ -2,19 +2,42 @@
 use image::io::Reader;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
-use std::error::Error;
 use std::io::Cursor;
-use std::rc::Rc;
+use std::sync::Arc;
 use warp::hyper::body::Bytes;
 
+mod builder;
+mod call;
+mod de;
+mod error;
+mod ids;
 mod oauth;
-pub use oauth::{ApiKey, Token as OauthToken};
+mod rate_limit;
+mod retry;
+mod transport;
+pub use builder::{Endpoints, FlickrAPIBuilder};
+pub use error::{Error, ErrorCode, FlickrError, LoginError};
+pub use ids::{GalleryId, GroupId, ParseIdError, PhotoId, PhotosetId, Secret, UserId};
+pub use oauth::{
+    ApiKey, AuthorizationMode, Clock, Identity, NonceSource, OauthErrorDescription, Permission,
+    RandomNonce, SystemClock, Token as OauthToken,
+};
+pub use rate_limit::{RateLimit, RateLimitStats};
+pub use retry::RetryPolicy;
+pub use transport::{HttpRequest, HttpResponse, ReqwestTransport, Transport, TransportFuture};
 
+pub mod cassette;
+pub mod check_token;
+pub mod get_access_token;
 pub mod get_info;
 pub mod get_sizes;
 pub mod login;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod test_login;
+pub mod token_store;
 pub mod upload_photo;
+pub mod urls;
 
 static URL_ACCESS: &str = "https://www.flickr.com/services/oauth/access_token";
 static URL_AUTHORIZE: &str = "https://www.flickr.com/services/oauth/authorize";
@@ -24,8 +47,13 @@ static URL_API: &str = "https://api.flickr.com/services/rest/";
 
 static URL_UPLOAD: &str = "https://up.flickr.com/services/upload/";
 
+pub use cassette::CassetteTransport;
+pub use check_token::TokenCheck;
 pub use get_sizes::FlickrSize;
+pub use login::{CallbackLogin, LoginOptions, PendingLogin};
 pub use test_login::UserData;
+pub use token_store::{FileTokenStore, MemoryTokenStore, TokenStore};
+pub use tokio_util::sync::CancellationToken;
 
 /// This is meant to turn the abominations the XML conversion creates into easier on the eyes
 /// structs:
@@ -42,67 +70,87 @@ fn deserialize_content<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
 {
+    // Flickr wraps text as `{"_content": "..."}`, the serialized form of this crate does not
     let v: Value = Deserialize::deserialize(deserializer)?;
-    Ok(v["_content"].as_str().unwrap_or("").to_string())
+    Ok(v.as_str()
+        .or(v["_content"].as_str())
+        .unwrap_or("")
+        .to_string())
 }
 
 trait Resultable<T, E> {
     fn to_result(self) -> Result<T, E>;
 }
 
-/// Common error type for all flickr API answers
-#[derive(Deserialize, Debug, Hash)]
-pub struct FlickrError {
-    pub stat: String,
-    pub code: u32,
-    pub message: String,
+/// Convenience function to download an image using a default client
+#[deprecated(note = "use FlickrAPI::download_image, which uses the client's settings")]
+pub async fn download_image(url: &str) -> Result<Reader<Cursor<Bytes>>, Error> {
+    FlickrAPI::new(ApiKey::default()).download_image(url).await
 }
 
-impl std::error::Error for FlickrError {}
-
-use std::fmt::Display;
-impl Display for FlickrError {
-    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        write!(formatter, "{} (code {})", self.message, self.code)
-    }
-}
-
-/// Convenience function to download an image using the library's client
-pub async fn download_image(url: &String) -> Result<Reader<Cursor<Bytes>>, Box<dyn Error>> {
-    let res = reqwest::get(url).await?;
-
-    Ok(Reader::new(Cursor::new(res.bytes().await?)))
-}
+/// `User-Agent` header sent by default
+static USER_AGENT: &str = concat!("flickr-api/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Clone)]
 struct FlickrAPIData {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
+    /// `None` to leave the header to the transport, e.g. an injected client's default
+    user_agent: Option<String>,
     key: ApiKey,
     token: Option<OauthToken>,
+    endpoints: Endpoints,
+    signer: oauth::Signer,
+    authorization: AuthorizationMode,
+    retry: RetryPolicy,
+    /// Shared by every client derived from this one
+    limiter: Arc<rate_limit::RateLimiter>,
+}
+
+impl FlickrAPIData {
+    /// Fail if the token was granted less than `required`
+    ///
+    /// Tokens of unknown access level are let through, for flickr to decide.
+    fn check_permission(&self, required: Permission) -> Result<(), Error> {
+        match self.token.as_ref().and_then(|token| token.perms) {
+            Some(granted) if granted < required => {
+                Err(Error::InsufficientPermission { required, granted })
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// API client
+///
+/// The client is cheap to clone and can be shared across threads and tasks.
+#[derive(Clone)]
 pub struct FlickrAPI {
-    data: Rc<FlickrAPIData>,
+    data: Arc<FlickrAPIData>,
 }
 
+#[derive(Clone)]
 pub struct PhotoRequestBuilder {
-    handle: Rc<FlickrAPIData>,
+    handle: Arc<FlickrAPIData>,
 }
 
+#[derive(Clone)]
 pub struct TestRequestBuilder {
-    handle: Rc<FlickrAPIData>,
+    handle: Arc<FlickrAPIData>,
+}
+
+#[derive(Clone)]
+pub struct AuthRequestBuilder {
+    handle: Arc<FlickrAPIData>,
 }
 
 impl FlickrAPI {
     pub fn new(key: ApiKey) -> Self {
-        let data = Rc::new(FlickrAPIData {
-            client: reqwest::Client::new(),
-            key,
-            token: None,
-        });
+        FlickrAPI::builder(key).build()
+    }
 
-        FlickrAPI { data }
+    /// Create a client with non-default settings
+    pub fn builder(key: ApiKey) -> FlickrAPIBuilder {
+        FlickrAPIBuilder::new(key)
     }
 
     pub fn with_token(self, token: OauthToken) -> Self {
@@ -110,7 +158,7 @@ impl FlickrAPI {
         data.token = Some(token);
 
         FlickrAPI {
-            data: Rc::new(data),
+            data: Arc::new(data),
         }
     }
 
@@ -118,6 +166,19 @@ impl FlickrAPI {
         self.data.token.clone()
     }
 
+    /// The account the client is logged in as, if known
+    pub fn user(&self) -> Option<&Identity> {
+        self.data
+            .token
+            .as_ref()
+            .and_then(|token| token.user.as_ref())
+    }
+
+    /// ID of the logged in user, to use as the default `user_id` of methods that need one
+    pub fn user_id(&self) -> Option<&UserId> {
+        self.user().map(|user| &user.nsid)
+    }
+
     pub fn photos(&self) -> PhotoRequestBuilder {
         PhotoRequestBuilder {
             handle: self.data.clone(),
@@ -129,4 +190,87 @@ impl FlickrAPI {
             handle: self.data.clone(),
         }
     }
+
+    pub fn auth(&self) -> AuthRequestBuilder {
+        AuthRequestBuilder {
+            handle: self.data.clone(),
+        }
+    }
+
+    /// Download an image, such as a size listed by [PhotoRequestBuilder::get_sizes], with the
+    /// client's HTTP settings
+    ///
+    /// The request is not signed and does not count towards the rate limit.
+    pub async fn download_image(&self, url: &str) -> Result<Reader<Cursor<Bytes>>, Error> {
+        let res = self.data.send(self.data.request("GET", url)).await?;
+
+        if !res.is_success() {
+            return Err(Error::Http {
+                status: res.status,
+                body: res.text(),
+            });
+        }
+
+        Ok(Reader::new(Cursor::new(Bytes::from(res.body))))
+    }
+}
+
+#[test]
+fn test_client_is_send_sync() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let client = FlickrAPI::new(ApiKey::default());
+    assert_send_sync(&client);
+    assert_send_sync(&client.photos());
+    assert_send_sync(&client.test());
+    assert_send_sync(&client.auth());
+}
+
+#[test]
+fn test_futures_are_send_sync() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let client = FlickrAPI::new(ApiKey::default());
+    let photos = client.photos();
+    let test = client.test();
+    assert_send_sync(&photos.get_info("1", None));
+    assert_send_sync(&photos.get_sizes("1"));
+    assert_send_sync(&photos.upload(&[], None));
+    assert_send_sync(&photos.upload_from_path(std::path::Path::new("photo.jpg")));
+    assert_send_sync(&test.login());
+    assert_send_sync(&client.auth().check_token());
+    assert_send_sync(&client.auth().get_access_token("legacy"));
+    assert_send_sync(&client.download_image("https://live.staticflickr.com/"));
+    assert_send_sync(&client.call("flickr.test.echo", [("name", "value")]));
+    assert_send_sync(&client.post("flickr.test.echo", [("name", "value")]));
+    assert_send_sync(&client.begin_login(Permission::Read));
+    assert_send_sync(&client.begin_callback_login(LoginOptions::default()));
+    assert_send_sync(&client.clone().login());
+    assert_send_sync(
+        &client
+            .clone()
+            .login_or_restore(&MemoryTokenStore::default()),
+    );
+}
+
+#[tokio::test]
+async fn test_permission_checked_locally() {
+    let client = FlickrAPI::builder(ApiKey::default())
+        .endpoints(Endpoints::with_base("http://127.0.0.1:1"))
+        .token(OauthToken {
+            perms: Some(Permission::Write),
+            ..Default::default()
+        })
+        .build();
+
+    match client
+        .call("flickr.photos.delete", [("photo_id", "1")])
+        .await
+    {
+        Err(Error::InsufficientPermission { required, granted }) => {
+            assert_eq!(required, Permission::Delete);
+            assert_eq!(granted, Permission::Write);
+        }
+        other => panic!("Unexpected answer: {other:?}"),
+    }
 }
@@ -1,7 +1,11 @@
 use crate::*;
-use futures::Future;
+use std::net::SocketAddr;
 use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use warp::path::FullPath;
 use warp::Filter;
 
 #[derive(Debug, Default, Deserialize, Clone)]
@@ -11,70 +15,327 @@ struct CallbackQuery {
     oauth_verifier: String,
 }
 
-fn setup_server() -> (u32, impl Future<Output = CallbackQuery>) {
-    let (answer_tx, mut answer_rx) = mpsc::unbounded_channel::<CallbackQuery>();
+/// Settings of the local server receiving the OAuth callback during [FlickrAPI::login_with]
+#[derive(Debug, Clone)]
+pub struct LoginOptions {
+    /// Address the server binds to. Use port 0 to let the system pick a free port
+    pub bind: SocketAddr,
+    /// Host name flickr redirects the user's browser to
+    pub host: String,
+    /// Path the callback is received on
+    pub path: String,
+    /// Access level asked to the user
+    pub permission: Permission,
+    /// Give up waiting for the user after this delay
+    pub timeout: Option<Duration>,
+    /// Give up waiting for the user once this token is cancelled
+    pub cancel: Option<CancellationToken>,
+    /// Page displayed to the user once access was granted
+    pub success_page: String,
+    /// Page displayed to the user if the callback could not be used
+    pub failure_page: String,
+}
+
+impl Default for LoginOptions {
+    fn default() -> Self {
+        LoginOptions {
+            bind: ([127, 0, 0, 1], 8200).into(),
+            host: "localhost".into(),
+            path: "/authorization".into(),
+            permission: Permission::Write,
+            timeout: None,
+            cancel: None,
+            success_page: "<!DOCTYPE html><html><head>Authentication succeeded. You may close this window.<script>window.close();</script></head><body></body></html>".into(),
+            failure_page: "<!DOCTYPE html><html><head>Authentication failed. Please try again.</head><body></body></html>".into(),
+        }
+    }
+}
+
+/// Handles to the running callback server
+struct CallbackServer {
+    addr: SocketAddr,
+    /// Must be set to the request token before the user is sent to flickr; callbacks for any
+    /// other token are refused
+    expected: Arc<OnceLock<String>>,
+    /// Verifiers of the callbacks carrying the expected token
+    answer: mpsc::UnboundedReceiver<String>,
+    /// Stops the server when dropped
+    shutdown: oneshot::Sender<()>,
+}
+
+/// Start the server receiving the callback
+///
+/// Callbacks for another token or without a verifier, such as a stale tab from an earlier login,
+/// are answered with the failure page and otherwise ignored.
+fn setup_server(options: &LoginOptions) -> Result<CallbackServer, Error> {
+    let (answer_tx, answer_rx) = mpsc::unbounded_channel::<String>();
+    let expected = Arc::new(OnceLock::<String>::new());
+
+    let path = options.path.clone();
+    let pages = (options.success_page.clone(), options.failure_page.clone());
+    let token = expected.clone();
+
     let authorization = warp::get()
-        .and(warp::path!("authorization"))
+        .and(warp::path::full())
         .and(warp::query::<CallbackQuery>())
-        .and(warp::any().map(move || answer_tx.clone()))
-        .and_then(
-            |data, sender: mpsc::UnboundedSender<CallbackQuery>| async move {
-                sender.send(data).ok();
-                Result::<String, warp::Rejection>::Ok("<!DOCTYPE html><html><head>Authentication succeeded. You may close this window.<script>window.close();</script></head><body></body></html>".into())
-            },
-        ).map(|reply| {
-        warp::reply::with_header(reply, "Content-Type", "text/html")
-    });
+        .and_then(move |full: FullPath, data: CallbackQuery| {
+            let accepted = full.as_str() == path;
+            let answer = if token.get() != Some(&data.oauth_token) {
+                Err(LoginError::TokenMismatch)
+            } else if data.oauth_verifier.is_empty() {
+                Err(LoginError::MissingVerifier)
+            } else {
+                Ok(data.oauth_verifier)
+            };
+            let page = match (accepted, answer) {
+                (false, _) => pages.1.clone(),
+                (true, Ok(verifier)) => {
+                    answer_tx.send(verifier).ok();
+                    pages.0.clone()
+                }
+                (true, Err(e)) => {
+                    log::warn!("Ignoring callback: {e}");
+                    pages.1.clone()
+                }
+            };
+
+            async move {
+                match accepted {
+                    true => Ok(warp::reply::html(page)),
+                    false => Err(warp::reject::not_found()),
+                }
+            }
+        });
 
     let (plug_tx, plug_rx) = oneshot::channel();
-    let (_addr, server) =
-        warp::serve(authorization).bind_with_graceful_shutdown(([127, 0, 0, 1], 8200), async {
+    let (addr, server) = warp::serve(authorization)
+        .try_bind_with_graceful_shutdown(options.bind, async {
             plug_rx.await.ok();
-        });
+        })
+        .map_err(|e| Error::Io(std::io::Error::other(e)))?;
 
     tokio::spawn(server);
 
-    (8200, async move {
-        let query = answer_rx.recv().await.unwrap();
-        plug_tx.send(()).ok();
-        query
+    Ok(CallbackServer {
+        addr,
+        expected,
+        answer: answer_rx,
+        shutdown: plug_tx,
     })
 }
 
-impl FlickrAPI {
-    /// Top-level method enacting the procesure to receive an access token from a set of API keys
+/// A login waiting for flickr to send the user back to the local callback server
+///
+/// The server shuts down when this is dropped or completed.
+pub struct CallbackLogin {
+    pending: PendingLogin,
+    local_addr: SocketAddr,
+    answer: mpsc::UnboundedReceiver<String>,
+    timeout: Option<Duration>,
+    cancel: Option<CancellationToken>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl CallbackLogin {
+    /// The page the user must visit to grant access
+    pub fn authorize_url(&self) -> &str {
+        self.pending.authorize_url()
+    }
+
+    /// The address the callback server is bound to, with the actual port if 0 was asked for
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait for the callback, then exchange the request token for an access token
     ///
-    /// This method opens an HTTP server on port 8200. It will log an url to connect to for the user to
-    /// accept the token, as well as use a generic open method to open the webpage (`open` on macos and
-    /// `xdg-open` on linux)
-    pub async fn login(self) -> Result<Self, Box<dyn Error>> {
-        // Open an HTTP server on localhost to point the callback to
-        let (port, answer) = setup_server();
-        let callback_url = format!("http://localhost:{}/authorization", port);
+    /// Callbacks that do not belong to this login are ignored until the right one arrives or the
+    /// login times out or is cancelled.
+    pub async fn complete(mut self) -> Result<FlickrAPI, Error> {
+        let timeout = async {
+            match self.timeout {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+        };
+        let cancelled = async {
+            match &self.cancel {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let verifier = tokio::select! {
+            answer = self.answer.recv() => answer.ok_or(LoginError::ServerClosed),
+            _ = timeout => Err(LoginError::Timeout),
+            _ = cancelled => Err(LoginError::Cancelled),
+        }?;
+
+        self.pending.complete(&verifier).await
+    }
+}
+
+/// A login waiting for the user to grant access
+///
+/// Send the user to [PendingLogin::authorize_url], then call [PendingLogin::complete] with the
+/// verifier flickr gives them.
+pub struct PendingLogin {
+    api: FlickrAPI,
+    request_token: OauthToken,
+    authorize_url: String,
+}
+
+impl PendingLogin {
+    /// The page the user must visit to grant access
+    pub fn authorize_url(&self) -> &str {
+        &self.authorize_url
+    }
+
+    /// Exchange the request token for an access token with the verifier, returning a client using
+    /// it
+    ///
+    /// The login is kept on failure, so a mistyped verifier can be entered again.
+    ///
+    /// The level granted, which can differ from the one asked for, is then read with
+    /// [AuthRequestBuilder::check_token]. It is left unknown if that check fails.
+    pub async fn complete(&self, verifier: &str) -> Result<FlickrAPI, Error> {
+        let data = &self.api.data;
+
+        let response: oauth::OauthAccessAnswer = {
+            let params = vec![("oauth_verifier", verifier.trim().to_string())];
+            let request = data.signed_request(
+                oauth::RequestTarget::Get(&data.endpoints.access),
+                params,
+                Some(&self.request_token),
+            )?;
+            let raw = data.send(request).await?.text();
+            serde_urlencoded::from_str(&raw)
+                .map_err(|source| Error::UrlEncoded { source, body: raw })?
+        };
+
+        let mut token: OauthToken = response.to_result()?;
+        match self
+            .api
+            .clone()
+            .with_token(token.clone())
+            .auth()
+            .check_token()
+            .await
+        {
+            Ok(check) => token.perms = Some(check.perms),
+            Err(e) => log::warn!("Could not check the access level granted: {e}"),
+        }
 
+        Ok(self.api.clone().with_token(token))
+    }
+}
+
+impl FlickrAPI {
+    /// Ask for a request token with the given callback and prepare the authorization link
+    async fn begin(
+        &self,
+        callback_url: String,
+        permission: Permission,
+    ) -> Result<PendingLogin, Error> {
         // Use the api keys to ask for a request token
         let response: oauth::OauthTokenAnswer = {
-            let mut params = vec![("oauth_callback", callback_url)];
-            oauth::build_request(
-                oauth::RequestTarget::Get(URL_REQUEST),
-                &mut params,
-                &self.data.key,
+            let params = vec![("oauth_callback", callback_url)];
+            let request = self.data.signed_request(
+                oauth::RequestTarget::Get(&self.data.endpoints.request),
+                params,
                 None,
-            );
-            let request = reqwest::Url::parse_with_params(URL_REQUEST, &params)?;
-            let query = self.data.client.get(request).send().await?;
-            serde_urlencoded::from_str(&query.text().await?)?
+            )?;
+            let raw = self.data.send(request).await?.text();
+            serde_urlencoded::from_str(&raw)
+                .map_err(|source| Error::UrlEncoded { source, body: raw })?
         };
 
-        let request_token = response.to_result()?;
+        let request_token: OauthToken = response.to_result()?;
 
         // Prepare the link for the user to grant permission
+        let params = vec![
+            ("oauth_token", request_token.token.clone()),
+            ("perms", permission.to_string()),
+        ];
+        let authorize_url =
+            reqwest::Url::parse_with_params(&self.data.endpoints.authorize, params)?.to_string();
+
+        Ok(PendingLogin {
+            api: self.clone(),
+            request_token,
+            authorize_url,
+        })
+    }
+
+    /// Start an out-of-band login, for environments where no browser or local server is available
+    ///
+    /// Once the user visits the [PendingLogin::authorize_url] and grants access, flickr displays
+    /// a verifier code to give to [PendingLogin::complete]:
+    /// ```no_run
+    /// # use flickr_api::Permission;
+    /// # async fn run(client: flickr_api::FlickrAPI) -> Result<(), flickr_api::Error> {
+    /// let pending = client.begin_login(Permission::Read).await?;
+    /// println!("Visit {}", pending.authorize_url());
+    ///
+    /// let mut verifier = String::new();
+    /// std::io::stdin().read_line(&mut verifier)?;
+    /// let client = pending.complete(&verifier).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn begin_login(&self, permission: Permission) -> Result<PendingLogin, Error> {
+        self.begin("oob".to_string(), permission).await
+    }
+
+    /// Start a login where flickr sends the user back to a local server once access is granted
+    ///
+    /// The server is bound before returning so the actual address can be read with
+    /// [CallbackLogin::local_addr]. Send the user to [CallbackLogin::authorize_url] and wait for
+    /// the callback with [CallbackLogin::complete].
+    pub async fn begin_callback_login(
+        &self,
+        options: LoginOptions,
+    ) -> Result<CallbackLogin, Error> {
+        // Open an HTTP server on localhost to point the callback to
+        let server = setup_server(&options)?;
+        let callback_url = format!(
+            "http://{}:{}{}",
+            options.host,
+            server.addr.port(),
+            options.path
+        );
+
+        let pending = self.begin(callback_url, options.permission).await?;
+        server
+            .expected
+            .set(pending.request_token.token.clone())
+            .ok();
+
+        Ok(CallbackLogin {
+            pending,
+            local_addr: server.addr,
+            answer: server.answer,
+            timeout: options.timeout,
+            cancel: options.cancel,
+            _shutdown: server.shutdown,
+        })
+    }
+
+    /// Top-level method enacting the procesure to receive an access token from a set of API keys
+    ///
+    /// This method opens an HTTP server on port 8200. It will log an url to connect to for the user to
+    /// accept the token, as well as use a generic open method to open the webpage (`open` on macos and
+    /// `xdg-open` on linux)
+    pub async fn login(self) -> Result<Self, Error> {
+        self.login_with(LoginOptions::default()).await
+    }
+
+    /// Same as [FlickrAPI::login], with the callback server configured by `options`
+    pub async fn login_with(self, options: LoginOptions) -> Result<Self, Error> {
+        let login = self.begin_callback_login(options).await?;
+
         {
-            let params = vec![
-                ("oauth_token", request_token.token.clone()),
-                ("perms", "write".to_string()),
-            ];
-            let url = reqwest::Url::parse_with_params(URL_AUTHORIZE, params)?.to_string();
+            let url = login.authorize_url().to_string();
 
             log::info!("OAuth link: {url}");
 
@@ -85,29 +346,183 @@ impl FlickrAPI {
             Command::new("xdg-open").args(vec![url]).spawn()?;
         }
 
-        // Wait for the HTTP server to receive the callback query once the user accepted
-        let callback_data = answer.await;
+        // Wait for the HTTP server to receive the callback query once the user accepted, then
+        // exchange the request token for an access token with the verifier received
+        login.complete().await
+    }
+}
 
-        // Exchange the request token for an access token with the verifier received above
-        let response: oauth::OauthAccessAnswer = {
-            let mut params = vec![("oauth_verifier", callback_data.oauth_verifier)];
-            oauth::build_request(
-                oauth::RequestTarget::Get(URL_ACCESS),
-                &mut params,
-                &self.data.key,
-                Some(&request_token),
-            );
-            let access = reqwest::Url::parse_with_params(URL_ACCESS, &params)?;
-            let query = self.data.client.get(access).send().await?;
-            serde_urlencoded::from_str(&query.text().await?)?
-        };
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_oob() {
+    use crate::mock::{test_key, MockServer};
 
-        let token = response.to_result()?;
-        let mut data = (*self.data).clone();
-        data.token = Some(token);
+    let server = MockServer::start(test_key()).await;
 
-        Ok(FlickrAPI {
-            data: Rc::new(data),
-        })
+    let pending = server.client().begin_login(Permission::Read).await.unwrap();
+
+    // Play the user's part: visit the page and copy the verifier
+    let verifier = server.authorize(pending.authorize_url()).await;
+
+    let client = pending.complete(&format!("{verifier}\n")).await.unwrap();
+    let user = client.test().login().await.unwrap();
+
+    assert_eq!(user.id, server.user().nsid);
+    assert_eq!(client.user_id(), Some(&user.id));
+    assert_eq!(client.user().unwrap().fullname, server.user().fullname);
+    assert_eq!(client.token().unwrap().perms, Some(Permission::Read));
+
+    // Uploading needs more than was granted
+    match client.photos().upload(b"", None).await {
+        Err(Error::InsufficientPermission { required, granted }) => {
+            assert_eq!(required, Permission::Write);
+            assert_eq!(granted, Permission::Read);
+        }
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_oob_wrong_verifier() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+
+    let pending = server
+        .client()
+        .begin_login(Permission::Write)
+        .await
+        .unwrap();
+
+    match pending.complete("000-000-000").await {
+        Err(Error::Oauth(problem)) => assert_eq!(problem.oauth_problem, "verifier_invalid"),
+        other => panic!("Unexpected answer: {:?}", other.map(|_| ())),
+    }
+
+    // The same login can be completed with the right verifier
+    let verifier = server.authorize(pending.authorize_url()).await;
+
+    let client = pending.complete(&verifier).await.unwrap();
+    assert_eq!(client.token().unwrap().perms, Some(Permission::Write));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_records_granted_permission() {
+    use crate::mock::{test_key, MockFailure, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    server.grant(Permission::Delete);
+
+    // Flickr granted more than was asked for
+    let pending = server.client().begin_login(Permission::Read).await.unwrap();
+    let verifier = server.authorize(pending.authorize_url()).await;
+    let client = pending.complete(&verifier).await.unwrap();
+    assert_eq!(client.token().unwrap().perms, Some(Permission::Delete));
+
+    // The level is left unknown when it cannot be checked
+    let pending = server.client().begin_login(Permission::Read).await.unwrap();
+    let verifier = server.authorize(pending.authorize_url()).await;
+    server.fail_next(1, MockFailure::ServiceUnavailable);
+    let client = pending.complete(&verifier).await.unwrap();
+    assert_eq!(client.token().unwrap().perms, None);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_callback() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+
+    let options = LoginOptions {
+        bind: ([127, 0, 0, 1], 0).into(),
+        path: "/done".into(),
+        success_page: "granted".into(),
+        ..Default::default()
+    };
+    let login = server.client().begin_callback_login(options).await.unwrap();
+    assert_ne!(login.local_addr().port(), 0);
+
+    // The mock grants access and redirects to the callback
+    let page = reqwest::get(login.authorize_url())
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(page, "granted");
+
+    let client = login.complete().await.unwrap();
+    let user = client.test().login().await.unwrap();
+
+    assert_eq!(user.id, server.user().nsid);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_callback_token_mismatch() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+
+    let options = LoginOptions {
+        bind: ([127, 0, 0, 1], 0).into(),
+        failure_page: "refused".into(),
+        ..Default::default()
+    };
+    let login = server.client().begin_callback_login(options).await.unwrap();
+
+    // Callbacks for another login are refused without ending this one
+    for query in [
+        "oauth_token=forged&oauth_verifier=123",
+        "oauth_token=forged",
+    ] {
+        let url = format!("http://{}/authorization?{query}", login.local_addr());
+        let page = reqwest::get(url).await.unwrap().text().await.unwrap();
+        assert_eq!(page, "refused");
+    }
+
+    // The mock grants access and redirects to the callback with the right token
+    reqwest::get(login.authorize_url()).await.unwrap();
+
+    let client = login.complete().await.unwrap();
+    let user = client.test().login().await.unwrap();
+
+    assert_eq!(user.id, server.user().nsid);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_callback_timeout_and_cancel() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+
+    let options = LoginOptions {
+        bind: ([127, 0, 0, 1], 0).into(),
+        timeout: Some(Duration::from_millis(50)),
+        ..Default::default()
+    };
+    let login = server.client().begin_callback_login(options).await.unwrap();
+
+    match login.complete().await {
+        Err(Error::Login(LoginError::Timeout)) => (),
+        other => panic!("Unexpected answer: {:?}", other.map(|_| ())),
+    }
+
+    let cancel = CancellationToken::new();
+    let options = LoginOptions {
+        bind: ([127, 0, 0, 1], 0).into(),
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    let login = server.client().begin_callback_login(options).await.unwrap();
+    cancel.cancel();
+
+    match login.complete().await {
+        Err(Error::Login(LoginError::Cancelled)) => (),
+        other => panic!("Unexpected answer: {:?}", other.map(|_| ())),
     }
 }
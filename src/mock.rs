@@ -0,0 +1,1189 @@
+//! In-process stand-in for the flickr API, meant for tests
+//!
+//! [MockServer] listens on a random local port and implements the OAuth dance, the REST methods
+//! wrapped by this crate and the upload endpoint. It checks the signature of every request it
+//! receives and keeps photos in memory so tests can seed and inspect them.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), flickr_api::Error> {
+//! use flickr_api::mock::{MockPhoto, MockServer};
+//! use flickr_api::{ApiKey, Permission};
+//!
+//! let server = MockServer::start(ApiKey {
+//!     key: "key".into(),
+//!     secret: "secret".into(),
+//! })
+//! .await;
+//! let id = server.add_photo(MockPhoto::new(vec![]));
+//!
+//! let client = server.logged_in(Permission::Read);
+//! let info = client.photos().get_info(&id, None).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::*;
+use futures_util::TryStreamExt;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Buf, Filter, Reply};
+
+/// The account every token issued by the server belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockUser {
+    pub nsid: String,
+    pub username: String,
+    pub fullname: String,
+}
+
+impl Default for MockUser {
+    fn default() -> Self {
+        MockUser {
+            nsid: "12345678@N00".into(),
+            username: "mock".into(),
+            fullname: "Mock User".into(),
+        }
+    }
+}
+
+/// A photo held by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockPhoto {
+    /// Assigned by the server if left empty
+    pub id: String,
+    /// NSID of the owner, set to the server's user if left empty
+    pub owner: String,
+    pub title: String,
+    pub description: String,
+    pub secret: String,
+    pub server: String,
+    pub farm: u32,
+    pub originalsecret: String,
+    pub originalformat: String,
+    /// Unix timestamp of the upload
+    pub dateuploaded: u64,
+    /// Contents of the original file
+    pub data: Vec<u8>,
+}
+
+impl MockPhoto {
+    /// A photo with the given contents and default metadata
+    pub fn new(data: Vec<u8>) -> Self {
+        MockPhoto {
+            id: String::new(),
+            owner: String::new(),
+            title: String::new(),
+            description: String::new(),
+            secret: "a1b2c3d4e5".into(),
+            server: "65535".into(),
+            farm: 66,
+            originalsecret: "f6e7d8c9b0".into(),
+            originalformat: "jpg".into(),
+            dateuploaded: now(),
+            data,
+        }
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        image::io::Reader::new(std::io::Cursor::new(&self.data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .unwrap_or((0, 0))
+    }
+}
+
+/// A transient failure the server answers with instead of handling a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFailure {
+    /// Error code 105, "Service currently unavailable"
+    ServiceUnavailable,
+    /// The given HTTP status with a plain text body
+    Status(u16),
+}
+
+impl MockFailure {
+    fn api_reply(self) -> Response {
+        match self {
+            MockFailure::ServiceUnavailable => api_error(105, "Service currently unavailable"),
+            MockFailure::Status(status) => status_reply(status),
+        }
+    }
+
+    fn upload_reply(self) -> Response {
+        match self {
+            MockFailure::ServiceUnavailable => upload_error(105, "Service currently unavailable"),
+            MockFailure::Status(status) => status_reply(status),
+        }
+    }
+}
+
+fn status_reply(status: u16) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let body = status.canonical_reason().unwrap_or_default();
+
+    warp::reply::with_status(body, status).into_response()
+}
+
+struct RequestToken {
+    secret: String,
+    callback: String,
+    verifier: Option<String>,
+    perms: String,
+}
+
+struct AccessToken {
+    secret: String,
+    perms: String,
+}
+
+/// Reasons a request can be refused before reaching the method
+enum Problem {
+    ConsumerKeyUnknown,
+    SignatureInvalid,
+    TokenRejected,
+    NonceUsed,
+    ParameterAbsent(&'static str),
+}
+
+impl Problem {
+    fn oauth(&self) -> String {
+        match self {
+            Problem::ConsumerKeyUnknown => "oauth_problem=consumer_key_unknown".into(),
+            Problem::SignatureInvalid => "oauth_problem=signature_invalid".into(),
+            Problem::TokenRejected => "oauth_problem=token_rejected".into(),
+            Problem::NonceUsed => "oauth_problem=nonce_used".into(),
+            Problem::ParameterAbsent(name) => {
+                format!("oauth_problem=parameter_absent&oauth_parameters_absent={name}")
+            }
+        }
+    }
+
+    fn api(&self) -> (u32, &'static str) {
+        match self {
+            Problem::ConsumerKeyUnknown => (100, "Invalid API Key (Key not found)"),
+            Problem::SignatureInvalid => (96, "Invalid signature"),
+            Problem::TokenRejected => (98, "Invalid auth token"),
+            Problem::NonceUsed => (96, "Invalid signature"),
+            Problem::ParameterAbsent(_) => (97, "Missing signature"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    key: ApiKey,
+    user: MockUser,
+    request_tokens: HashMap<String, RequestToken>,
+    access_tokens: HashMap<String, AccessToken>,
+    /// Tokens of the legacy authentication API, with their permissions
+    legacy_tokens: HashMap<String, String>,
+    photos: BTreeMap<String, MockPhoto>,
+    /// Timestamps and nonces of the requests accepted so far, to refuse replays
+    nonces: HashSet<(String, String)>,
+    /// Failures to answer the next REST and upload requests with
+    failures: VecDeque<MockFailure>,
+    /// `User-Agent` header of every request received
+    user_agents: Vec<String>,
+    /// Level granted at the authorization page instead of the one asked for
+    granted: Option<Permission>,
+    counter: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn oauth_reply(status: StatusCode, body: String) -> Response {
+    warp::reply::with_status(body, status).into_response()
+}
+
+fn api_error(code: u32, message: &str) -> Response {
+    warp::reply::json(&serde_json::json!({
+        "stat": "fail",
+        "code": code,
+        "message": message,
+    }))
+    .into_response()
+}
+
+fn api_ok(mut answer: Value) -> Response {
+    answer["stat"] = "ok".into();
+    warp::reply::json(&answer).into_response()
+}
+
+fn upload_reply(content: String) -> Response {
+    warp::reply::with_header(content, "Content-Type", "text/xml").into_response()
+}
+
+fn upload_error(code: u32, message: &str) -> Response {
+    upload_reply(format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?><rsp stat="fail"><err code="{code}" msg="{message}"/></rsp>"#
+    ))
+}
+
+impl State {
+    fn next(&mut self) -> u64 {
+        self.counter += 1;
+        self.counter
+    }
+
+    fn new_token(&mut self) -> (String, String) {
+        let n = self.next();
+        (
+            format!("72157{n:012}-{:016x}", n.wrapping_mul(0x9e3779b97f4a7c15)),
+            format!("{:016x}", n.wrapping_mul(0xc2b2ae3d27d4eb4f)),
+        )
+    }
+
+    /// Check the signature of a request, returning the token it was signed with
+    fn verify(
+        &mut self,
+        method: &str,
+        url: &str,
+        params: &[(String, String)],
+    ) -> Result<Option<String>, Problem> {
+        if param(params, "oauth_consumer_key") != Some(self.key.key.as_str()) {
+            return Err(Problem::ConsumerKeyUnknown);
+        }
+
+        if param(params, "api_key").is_some_and(|key| key != self.key.key) {
+            return Err(Problem::ConsumerKeyUnknown);
+        }
+
+        let signature =
+            param(params, "oauth_signature").ok_or(Problem::ParameterAbsent("oauth_signature"))?;
+
+        let token = param(params, "oauth_token");
+        let token_secret = match token {
+            None => "",
+            Some(token) => self
+                .access_tokens
+                .get(token)
+                .map(|t| t.secret.as_str())
+                .or_else(|| self.request_tokens.get(token).map(|t| t.secret.as_str()))
+                .ok_or(Problem::TokenRejected)?,
+        };
+
+        let unsigned = params
+            .iter()
+            .filter(|(k, _)| k != "oauth_signature")
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        let key = oauth::signing_key(&self.key.secret, Some(token_secret));
+
+        if oauth::signature(method, url, &unsigned, &key) != signature {
+            return Err(Problem::SignatureInvalid);
+        }
+
+        let once = (
+            param(params, "oauth_timestamp")
+                .unwrap_or_default()
+                .to_string(),
+            param(params, "oauth_nonce").unwrap_or_default().to_string(),
+        );
+        if !self.nonces.insert(once) {
+            return Err(Problem::NonceUsed);
+        }
+
+        Ok(token.map(String::from))
+    }
+
+    fn request_token(&mut self, url: &str, params: Vec<(String, String)>) -> Response {
+        if let Err(problem) = self.verify("GET", url, &params) {
+            return oauth_reply(StatusCode::UNAUTHORIZED, problem.oauth());
+        }
+
+        let Some(callback) = param(&params, "oauth_callback").map(String::from) else {
+            return oauth_reply(
+                StatusCode::BAD_REQUEST,
+                Problem::ParameterAbsent("oauth_callback").oauth(),
+            );
+        };
+
+        let (token, secret) = self.new_token();
+        let body = serde_urlencoded::to_string([
+            ("oauth_callback_confirmed", "true"),
+            ("oauth_token", &token),
+            ("oauth_token_secret", &secret),
+        ])
+        .unwrap();
+
+        self.request_tokens.insert(
+            token,
+            RequestToken {
+                secret,
+                callback,
+                verifier: None,
+                perms: String::new(),
+            },
+        );
+
+        oauth_reply(StatusCode::OK, body)
+    }
+
+    /// The user always grants access. The verifier is sent to the callback, or displayed if the
+    /// callback is `oob`.
+    fn authorize(&mut self, params: Vec<(String, String)>) -> Response {
+        let verifier = format!("{:03}-{:03}-{:03}", self.next() % 1000, 42, 7);
+
+        let token = param(&params, "oauth_token").unwrap_or_default();
+        let Some(request) = self.request_tokens.get_mut(token) else {
+            return oauth_reply(StatusCode::BAD_REQUEST, Problem::TokenRejected.oauth());
+        };
+
+        request.verifier = Some(verifier.clone());
+        request.perms = match self.granted {
+            Some(granted) => granted.to_string(),
+            None => param(&params, "perms").unwrap_or("read").to_string(),
+        };
+
+        if request.callback == "oob" {
+            return warp::reply::html(format!(
+                "<!DOCTYPE html><html><body><span id=\"verifier\">{verifier}</span></body></html>"
+            ))
+            .into_response();
+        }
+
+        match reqwest::Url::parse_with_params(
+            &request.callback,
+            [("oauth_token", token), ("oauth_verifier", &verifier)],
+        )
+        .ok()
+        .and_then(|url| url.as_str().parse::<warp::http::Uri>().ok())
+        {
+            Some(location) => warp::redirect::found(location).into_response(),
+            None => oauth_reply(
+                StatusCode::BAD_REQUEST,
+                "oauth_problem=callback_invalid".into(),
+            ),
+        }
+    }
+
+    fn access_token(&mut self, url: &str, params: Vec<(String, String)>) -> Response {
+        let token = match self.verify("GET", url, &params) {
+            Ok(Some(token)) => token,
+            Ok(None) => {
+                return oauth_reply(
+                    StatusCode::BAD_REQUEST,
+                    Problem::ParameterAbsent("oauth_token").oauth(),
+                )
+            }
+            Err(problem) => return oauth_reply(StatusCode::UNAUTHORIZED, problem.oauth()),
+        };
+
+        let verified = self.request_tokens.get(&token).is_some_and(|request| {
+            request.verifier.is_some()
+                && request.verifier.as_deref() == param(&params, "oauth_verifier")
+        });
+
+        if !verified {
+            return oauth_reply(
+                StatusCode::UNAUTHORIZED,
+                "oauth_problem=verifier_invalid".into(),
+            );
+        }
+
+        let request = self.request_tokens.remove(&token).unwrap();
+        let (token, secret) = self.new_token();
+        let body = serde_urlencoded::to_string([
+            ("fullname", self.user.fullname.as_str()),
+            ("oauth_token", &token),
+            ("oauth_token_secret", &secret),
+            ("user_nsid", &self.user.nsid),
+            ("username", &self.user.username),
+        ])
+        .unwrap();
+
+        self.access_tokens.insert(
+            token,
+            AccessToken {
+                secret,
+                perms: request.perms,
+            },
+        );
+
+        oauth_reply(StatusCode::OK, body)
+    }
+
+    fn rest(
+        &mut self,
+        host: &str,
+        http_method: &str,
+        url: &str,
+        params: Vec<(String, String)>,
+    ) -> Response {
+        let token = match self.verify(http_method, url, &params) {
+            Ok(token) => token,
+            Err(problem) => {
+                let (code, message) = problem.api();
+                return api_error(code, message);
+            }
+        };
+
+        if let Some(failure) = self.failures.pop_front() {
+            return failure.api_reply();
+        }
+
+        let method = param(&params, "method").unwrap_or_default();
+        let photo = param(&params, "photo_id").and_then(|id| self.photos.get(id));
+
+        match (method, photo) {
+            ("flickr.test.login", _) => match token {
+                Some(_) => api_ok(serde_json::json!({
+                    "user": {
+                        "id": self.user.nsid,
+                        "username": {"_content": self.user.username},
+                    }
+                })),
+                None => api_error(
+                    99,
+                    "Insufficient permissions. Method requires read privileges; none granted.",
+                ),
+            },
+            ("flickr.auth.oauth.checkToken", _) => {
+                match token.as_ref().and_then(|t| self.access_tokens.get(t)) {
+                    Some(access) => api_ok(serde_json::json!({
+                        "oauth": {
+                            "token": {"_content": token},
+                            "perms": {"_content": access.perms},
+                            "user": {
+                                "nsid": self.user.nsid,
+                                "username": self.user.username,
+                                "fullname": self.user.fullname,
+                            },
+                        }
+                    })),
+                    None => api_error(98, "Invalid token"),
+                }
+            }
+            ("flickr.auth.oauth.getAccessToken", _) => {
+                let legacy = param(&params, "auth_token").unwrap_or_default();
+                match self.legacy_tokens.remove(legacy) {
+                    Some(perms) => {
+                        let (token, secret) = self.new_token();
+                        let answer = serde_json::json!({
+                            "auth": {
+                                "access_token": {
+                                    "oauth_token": token,
+                                    "oauth_token_secret": secret,
+                                }
+                            }
+                        });
+                        self.access_tokens
+                            .insert(token, AccessToken { secret, perms });
+                        api_ok(answer)
+                    }
+                    None => api_error(98, "Invalid auth token"),
+                }
+            }
+            ("flickr.photos.setMeta", Some(_)) => {
+                let perms = token
+                    .as_ref()
+                    .and_then(|t| self.access_tokens.get(t))
+                    .map(|t| t.perms.as_str());
+                if !matches!(perms, Some("write" | "delete")) {
+                    return api_error(
+                        99,
+                        "Insufficient permissions. Method requires write privileges.",
+                    );
+                }
+
+                let title = param(&params, "title").map(String::from);
+                let description = param(&params, "description").map(String::from);
+                let photo = self
+                    .photos
+                    .get_mut(param(&params, "photo_id").unwrap_or_default())
+                    .unwrap();
+                if let Some(title) = title {
+                    photo.title = title;
+                }
+                if let Some(description) = description {
+                    photo.description = description;
+                }
+
+                api_ok(serde_json::json!({}))
+            }
+            ("flickr.photos.getInfo", Some(photo)) => api_ok(self.photo_info(photo)),
+            ("flickr.photos.getSizes", Some(photo)) => api_ok(photo_sizes(host, photo)),
+            (
+                "flickr.photos.getInfo" | "flickr.photos.getSizes" | "flickr.photos.setMeta",
+                None,
+            ) => api_error(1, "Photo not found"),
+            (method, _) => api_error(112, &format!("Method \"{method}\" not found")),
+        }
+    }
+
+    fn photo_info(&self, photo: &MockPhoto) -> Value {
+        serde_json::json!({
+            "photo": {
+                "id": photo.id,
+                "secret": photo.secret,
+                "server": photo.server,
+                "farm": photo.farm,
+                "dateuploaded": photo.dateuploaded.to_string(),
+                "isfavorite": 0,
+                "license": "0",
+                "safety_level": "0",
+                "rotation": 0,
+                "originalsecret": photo.originalsecret,
+                "originalformat": photo.originalformat,
+                "owner": {
+                    "nsid": photo.owner,
+                    "username": self.user.username,
+                    "realname": self.user.fullname,
+                    "location": "",
+                    "iconserver": "0",
+                    "iconfarm": 0,
+                    "path_alias": null,
+                },
+                "title": {"_content": photo.title},
+                "description": {"_content": photo.description},
+                "visibility": {"ispublic": 1, "isfriend": 0, "isfamily": 0},
+                "dates": {
+                    "posted": photo.dateuploaded.to_string(),
+                    "taken": "2024-01-01 00:00:00",
+                    "takengranularity": 0,
+                    "takenunknown": "1",
+                    "lastupdate": photo.dateuploaded.to_string(),
+                },
+                "views": "0",
+                "permissions": {"permcomment": 3, "permaddmeta": 2},
+                "editability": {"cancomment": 1, "canaddmeta": 1},
+                "publiceditability": {"cancomment": 1, "canaddmeta": 0},
+                "usage": {"candownload": 1, "canblog": 1, "canprint": 1, "canshare": 1},
+                "comments": {"_content": "0"},
+                "location": "",
+                "geoperms": {"ispublic": 0, "iscontact": 0, "isfriend": 0, "isfamily": 0},
+                "notes": {"note": []},
+                "people": {"haspeople": 0},
+                "tags": {"tag": []},
+                "urls": {"url": [{
+                    "type": "photopage",
+                    "_content": format!("https://www.flickr.com/photos/{}/{}/", photo.owner, photo.id),
+                }]},
+                "media": "photo",
+            }
+        })
+    }
+
+    fn upload(
+        &mut self,
+        url: &str,
+        fields: Vec<(String, String)>,
+        file: Option<(String, Vec<u8>)>,
+    ) -> Response {
+        let perms = match self.verify("POST", url, &fields) {
+            Ok(Some(token)) => self.access_tokens.get(&token).map(|t| t.perms.clone()),
+            Ok(None) => None,
+            Err(problem) => {
+                let (code, message) = problem.api();
+                return upload_error(code, message);
+            }
+        };
+
+        if let Some(failure) = self.failures.pop_front() {
+            return failure.upload_reply();
+        }
+
+        if !matches!(perms.as_deref(), Some("write" | "delete")) {
+            return upload_error(99, "Insufficient permissions");
+        }
+
+        let Some((filename, data)) = file else {
+            return upload_error(2, "No photo specified");
+        };
+
+        let mut photo = MockPhoto::new(data);
+        photo.title = filename
+            .rsplit_once('.')
+            .map(|(stem, _)| stem.to_string())
+            .unwrap_or(filename);
+        let id = self.add_photo(photo);
+
+        upload_reply(format!(
+            r#"<?xml version="1.0" encoding="utf-8" ?><rsp stat="ok"><photoid>{id}</photoid></rsp>"#
+        ))
+    }
+
+    fn add_photo(&mut self, mut photo: MockPhoto) -> String {
+        if photo.id.is_empty() {
+            photo.id = (54000000000 + self.next()).to_string();
+        }
+
+        if photo.owner.is_empty() {
+            photo.owner = self.user.nsid.clone();
+        }
+
+        let id = photo.id.clone();
+        self.photos.insert(id.clone(), photo);
+        id
+    }
+
+    /// Serve the contents of a photo from a static URL, whatever the size requested
+    fn image(&self, file: &str) -> Response {
+        let id = file.split('_').next().unwrap_or_default();
+
+        match self.photos.get(id) {
+            Some(photo) => photo.data.clone().into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+}
+
+fn photo_sizes(host: &str, photo: &MockPhoto) -> Value {
+    let (width, height) = photo.dimensions();
+    let scaled = |edge: u32| {
+        let longest = width.max(height).max(1);
+        let ratio = f64::from(edge.min(longest)) / f64::from(longest);
+        (
+            (f64::from(width) * ratio).round() as u32,
+            (f64::from(height) * ratio).round() as u32,
+        )
+    };
+    let source = |suffix: &str| {
+        format!(
+            "http://{host}/static/{}/{}_{}_{suffix}.jpg",
+            photo.server, photo.id, photo.secret
+        )
+    };
+    let (thumb_width, thumb_height) = scaled(100);
+
+    serde_json::json!({
+        "sizes": {
+            "canblog": 1,
+            "canprint": 1,
+            "candownload": 1,
+            "size": [
+                {"label": "Square", "width": 75, "height": 75, "source": source("s"), "media": "photo"},
+                {"label": "Thumbnail", "width": thumb_width, "height": thumb_height, "source": source("t"), "media": "photo"},
+                {
+                    "label": "Original",
+                    "width": width,
+                    "height": height,
+                    "source": format!(
+                        "http://{host}/static/{}/{}_{}_o.{}",
+                        photo.server, photo.id, photo.originalsecret, photo.originalformat
+                    ),
+                    "media": "photo",
+                },
+            ]
+        }
+    })
+}
+
+type Shared = Arc<Mutex<State>>;
+
+/// Parameters sent in an `Authorization: OAuth ...` header
+fn authorization_params(header: &str) -> Vec<(String, String)> {
+    let Some(fields) = header.strip_prefix("OAuth ") else {
+        return vec![];
+    };
+
+    let encoded = fields
+        .split(',')
+        .filter_map(|field| field.trim().split_once('='))
+        .filter(|(k, _)| *k != "realm")
+        .map(|(k, v)| format!("{k}={}", v.trim_matches('"')))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    url::form_urlencoded::parse(encoded.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+/// Read every part of an upload form, returning the text fields and the `photo` file
+async fn read_form(
+    form: warp::multipart::FormData,
+) -> Result<(Vec<(String, String)>, Option<(String, Vec<u8>)>), warp::Error> {
+    let parts = form
+        .and_then(|part| async move {
+            let name = part.name().to_string();
+            let filename = part.filename().map(String::from);
+            let data = part
+                .stream()
+                .try_fold(Vec::new(), |mut data, chunk| async move {
+                    data.extend_from_slice(chunk.chunk());
+                    Ok(data)
+                })
+                .await?;
+
+            Ok((name, filename, data))
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let mut fields = vec![];
+    let mut file = None;
+    for (name, filename, data) in parts {
+        match filename {
+            Some(filename) if name == "photo" => file = Some((filename, data)),
+            _ => fields.push((name, String::from_utf8_lossy(&data).into_owned())),
+        }
+    }
+
+    Ok((fields, file))
+}
+
+fn routes(state: Shared) -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone {
+    let state = warp::any().map(move || state.clone());
+    let record = warp::header::optional::<String>("user-agent")
+        .and(state.clone())
+        .map(|agent: Option<String>, state: Shared| {
+            state
+                .lock()
+                .unwrap()
+                .user_agents
+                .push(agent.unwrap_or_default())
+        })
+        .untuple_one();
+    let host = warp::header::<String>("host");
+    // Parameters from the query string and the `Authorization` header
+    let query = warp::query::<Vec<(String, String)>>()
+        .and(warp::header::optional::<String>("authorization"))
+        .map(
+            |mut params: Vec<(String, String)>, header: Option<String>| {
+                params.extend(
+                    header
+                        .as_deref()
+                        .map(authorization_params)
+                        .unwrap_or_default(),
+                );
+                params
+            },
+        );
+
+    let request_token = warp::get()
+        .and(warp::path!("services" / "oauth" / "request_token"))
+        .and(host)
+        .and(query)
+        .and(state.clone())
+        .map(|host: String, params, state: Shared| {
+            let url = format!("http://{host}/services/oauth/request_token");
+            state.lock().unwrap().request_token(&url, params)
+        });
+
+    let authorize = warp::get()
+        .and(warp::path!("services" / "oauth" / "authorize"))
+        .and(query)
+        .and(state.clone())
+        .map(|params, state: Shared| state.lock().unwrap().authorize(params));
+
+    let access_token = warp::get()
+        .and(warp::path!("services" / "oauth" / "access_token"))
+        .and(host)
+        .and(query)
+        .and(state.clone())
+        .map(|host: String, params, state: Shared| {
+            let url = format!("http://{host}/services/oauth/access_token");
+            state.lock().unwrap().access_token(&url, params)
+        });
+
+    let rest = warp::get()
+        .and(warp::path!("services" / "rest" / ..))
+        .and(host)
+        .and(query)
+        .and(state.clone())
+        .map(|host: String, params, state: Shared| {
+            let url = format!("http://{host}/services/rest/");
+            state.lock().unwrap().rest(&host, "GET", &url, params)
+        });
+
+    let rest_form = warp::post()
+        .and(warp::path!("services" / "rest" / ..))
+        .and(host)
+        .and(query)
+        .and(warp::body::form::<Vec<(String, String)>>())
+        .and(state.clone())
+        .map(
+            |host: String, mut params: Vec<(String, String)>, body, state: Shared| {
+                let url = format!("http://{host}/services/rest/");
+                params.extend(body);
+                state.lock().unwrap().rest(&host, "POST", &url, params)
+            },
+        );
+
+    let upload = warp::post()
+        .and(warp::path!("services" / "upload" / ..))
+        .and(host)
+        .and(query)
+        .and(warp::multipart::form().max_length(None))
+        .and(state.clone())
+        .and_then(
+            |host: String, params: Vec<_>, form, state: Shared| async move {
+                let url = format!("http://{host}/services/upload/");
+                let reply = match read_form(form).await {
+                    Ok((mut fields, file)) => {
+                        fields.extend(params);
+                        state.lock().unwrap().upload(&url, fields, file)
+                    }
+                    Err(_) => upload_error(2, "No photo specified"),
+                };
+
+                Result::<Response, warp::Rejection>::Ok(reply)
+            },
+        );
+
+    let image = warp::get()
+        .and(warp::path!("static" / String / String))
+        .and(state)
+        .map(|_server, file: String, state: Shared| state.lock().unwrap().image(&file));
+
+    let routes = request_token
+        .or(authorize)
+        .unify()
+        .or(access_token)
+        .unify()
+        .or(rest)
+        .unify()
+        .or(rest_form)
+        .unify()
+        .or(upload)
+        .unify()
+        .or(image)
+        .unify();
+
+    record.and(routes)
+}
+
+/// A flickr stand-in listening on a local port
+///
+/// The server shuts down when dropped. It must be started from within a tokio runtime.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Shared,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Start a server accepting requests signed with the given API key
+    pub async fn start(key: ApiKey) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            key,
+            ..Default::default()
+        }));
+
+        let (shutdown, signal) = oneshot::channel();
+        let (addr, server) = warp::serve(routes(state.clone())).bind_with_graceful_shutdown(
+            ([127, 0, 0, 1], 0),
+            async {
+                signal.await.ok();
+            },
+        );
+
+        tokio::spawn(server);
+
+        MockServer {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// The address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Endpoints pointing to this server, to give to [FlickrAPIBuilder::endpoints]
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::with_base(&format!("http://{}", self.addr))
+    }
+
+    /// A client using the server's API key and endpoints
+    pub fn client(&self) -> FlickrAPI {
+        FlickrAPI::builder(self.state.lock().unwrap().key.clone())
+            .endpoints(self.endpoints())
+            .build()
+    }
+
+    /// A client using the server's API key and endpoints, with a token of `permission`
+    pub fn logged_in(&self, permission: Permission) -> FlickrAPI {
+        self.client().with_token(self.issue_token(permission))
+    }
+
+    /// The account tokens are issued for
+    pub fn user(&self) -> MockUser {
+        self.state.lock().unwrap().user.clone()
+    }
+
+    pub fn set_user(&self, user: MockUser) {
+        self.state.lock().unwrap().user = user;
+    }
+
+    /// Issue an access token without going through the OAuth dance
+    pub fn issue_token(&self, permission: Permission) -> OauthToken {
+        let mut state = self.state.lock().unwrap();
+        let (token, secret) = state.new_token();
+
+        state.access_tokens.insert(
+            token.clone(),
+            AccessToken {
+                secret: secret.clone(),
+                perms: permission.to_string(),
+            },
+        );
+
+        OauthToken {
+            token,
+            secret,
+            perms: Some(permission),
+            user: Some(Identity {
+                nsid: state.user.nsid.clone().into(),
+                username: state.user.username.clone(),
+                fullname: state.user.fullname.clone(),
+            }),
+        }
+    }
+
+    /// Issue a token of the legacy authentication API, to exchange with
+    /// `flickr.auth.oauth.getAccessToken`
+    pub fn issue_legacy_token(&self, permission: Permission) -> String {
+        let mut state = self.state.lock().unwrap();
+        let token = format!("72157{:012}-legacy", state.next());
+
+        state
+            .legacy_tokens
+            .insert(token.clone(), permission.to_string());
+
+        token
+    }
+
+    /// Store a photo, returning its ID
+    pub fn add_photo(&self, photo: MockPhoto) -> PhotoId {
+        self.state.lock().unwrap().add_photo(photo).into()
+    }
+
+    pub fn photo(&self, id: impl AsRef<str>) -> Option<MockPhoto> {
+        self.state.lock().unwrap().photos.get(id.as_ref()).cloned()
+    }
+
+    /// All the photos stored, ordered by ID
+    pub fn photos(&self) -> Vec<MockPhoto> {
+        self.state
+            .lock()
+            .unwrap()
+            .photos
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub fn remove_photo(&self, id: impl AsRef<str>) -> Option<MockPhoto> {
+        self.state.lock().unwrap().photos.remove(id.as_ref())
+    }
+
+    /// Play the user's part of an out-of-band login: visit the authorization page at `url` and
+    /// return the verifier it displays
+    pub async fn authorize(&self, url: &str) -> String {
+        let page = reqwest::get(url).await.unwrap().text().await.unwrap();
+
+        page.split("<span id=\"verifier\">")
+            .nth(1)
+            .and_then(|rest| rest.split('<').next())
+            .expect("the authorization page displays a verifier")
+            .to_string()
+    }
+
+    /// `User-Agent` header of every request received so far, in order
+    pub fn user_agents(&self) -> Vec<String> {
+        self.state.lock().unwrap().user_agents.clone()
+    }
+
+    /// Grant `permission` to the logins authorized from now on, whatever they asked for, like
+    /// flickr does for an application the user already granted more to
+    pub fn grant(&self, permission: Permission) {
+        self.state.lock().unwrap().granted = Some(permission);
+    }
+
+    /// Answer the next `count` correctly signed REST or upload requests with `failure`
+    pub fn fail_next(&self, count: usize, failure: MockFailure) {
+        let mut state = self.state.lock().unwrap();
+        state.failures.extend(std::iter::repeat_n(failure, count));
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+/// The key every test server is started with
+#[cfg(test)]
+pub(crate) fn test_key() -> ApiKey {
+    ApiKey {
+        key: "0123456789abcdef".into(),
+        secret: "fedcba9876543210".into(),
+    }
+}
+
+#[tokio::test]
+async fn test_mock_rest() {
+    let server = MockServer::start(test_key()).await;
+    let mut photo = MockPhoto::new(vec![]);
+    photo.title = "Sunset".into();
+    let id = server.add_photo(photo);
+
+    let client = server.logged_in(Permission::Write);
+
+    let user = client.test().login().await.unwrap();
+    assert_eq!(user.id, server.user().nsid);
+
+    let info = client.photos().get_info(&id, None).await.unwrap();
+    assert_eq!(info.id, id);
+    assert_eq!(info.title, "Sunset");
+
+    let sizes = client.photos().get_sizes(&id).await.unwrap();
+    assert!(sizes.iter().any(|size| size.label == "Original"));
+
+    match client.photos().get_info("1", None).await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::MethodSpecific(1)),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_mock_rejects_bad_signature() {
+    let server = MockServer::start(test_key()).await;
+    let mut key = test_key();
+    key.secret = "wrong".into();
+
+    let client = FlickrAPI::builder(key)
+        .endpoints(server.endpoints())
+        .build();
+
+    match client.test().login().await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::InvalidSignature),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+
+    let client = server.client().with_token(OauthToken {
+        token: "unknown".into(),
+        secret: "unknown".into(),
+        ..Default::default()
+    });
+
+    match client.test().login().await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::InvalidAuthToken),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_mock_rejects_replayed_nonce() {
+    struct Constant;
+
+    impl NonceSource for Constant {
+        fn nonce(&self) -> String {
+            "constant".into()
+        }
+    }
+
+    let server = MockServer::start(test_key()).await;
+    let client = FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .token(server.issue_token(Permission::Read))
+        .clock(|| 1_700_000_000)
+        .nonces(Constant)
+        .build();
+
+    client.test().login().await.unwrap();
+    match client.test().login().await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::InvalidSignature),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+
+    // Random nonces never collide, even for requests sent at the same time
+    let client = server.logged_in(Permission::Read);
+    let test = client.test();
+    let logins = (0..16).map(|_| test.login());
+    for login in futures::future::join_all(logins).await {
+        login.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_mock_upload() {
+    let server = MockServer::start(test_key()).await;
+    let client = server.logged_in(Permission::Write);
+
+    let id = client
+        .photos()
+        .upload(b"not really a jpeg", Some("holidays.jpg".into()))
+        .await
+        .unwrap();
+
+    let photo = server.photo(&id).unwrap();
+    assert_eq!(photo.title, "holidays");
+    assert_eq!(photo.data, b"not really a jpeg");
+
+    match server.client().photos().upload(b"", None).await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::InsufficientPermissions),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_mock_oauth() {
+    let server = MockServer::start(test_key()).await;
+    let endpoints = server.endpoints();
+    let http = reqwest::Client::new();
+
+    let mut params = vec![("oauth_callback", "oob".to_string())];
+    oauth::build_request(
+        oauth::RequestTarget::Get(&endpoints.request),
+        &mut params,
+        &test_key(),
+        None,
+    );
+    let url = reqwest::Url::parse_with_params(&endpoints.request, &params).unwrap();
+    let raw = http.get(url).send().await.unwrap().text().await.unwrap();
+    let answer: oauth::OauthTokenAnswer = serde_urlencoded::from_str(&raw).unwrap();
+    let request_token: OauthToken = answer.to_result().unwrap();
+
+    let url = reqwest::Url::parse_with_params(
+        &endpoints.authorize,
+        [("oauth_token", &request_token.token)],
+    )
+    .unwrap();
+    let verifier = server.authorize(url.as_str()).await;
+
+    let mut params = vec![("oauth_verifier", verifier)];
+    oauth::build_request(
+        oauth::RequestTarget::Get(&endpoints.access),
+        &mut params,
+        &test_key(),
+        Some(&request_token),
+    );
+    let url = reqwest::Url::parse_with_params(&endpoints.access, &params).unwrap();
+    let raw = http.get(url).send().await.unwrap().text().await.unwrap();
+    let answer: oauth::OauthAccessAnswer = serde_urlencoded::from_str(&raw).unwrap();
+    let token: OauthToken = answer.to_result().unwrap();
+
+    let user = server
+        .client()
+        .with_token(token)
+        .test()
+        .login()
+        .await
+        .unwrap();
+    assert_eq!(user.username, server.user().username);
+}
@@ -1,18 +1,140 @@
-use crate::Resultable;
+use crate::{Error, Resultable, UserId};
 use data_encoding::BASE64;
 use hmac::{Hmac, Mac};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use url::form_urlencoded;
 
 type HmacSha1 = Hmac<sha1::Sha1>;
 
+/// Access level granted to a token, from the least to the most privileged
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Read private information
+    Read,
+    /// Add, edit and replace photos and their metadata. Includes `read`
+    Write,
+    /// Delete photos. Includes `write` and `read`
+    Delete,
+}
+
+impl Permission {
+    /// The value used by flickr for this level
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Delete => "delete",
+        }
+    }
+
+    /// The level a REST method is known to need
+    ///
+    /// Only the methods flickr documents as needing `write` or `delete` are listed. Every other
+    /// method, including ones added to the API later, maps to `read`: every token has it, so they
+    /// are never refused locally and flickr decides.
+    pub fn required_by(method: &str) -> Permission {
+        match method {
+            "flickr.photos.delete" => Permission::Delete,
+
+            "flickr.blogs.postPhoto"
+            | "flickr.favorites.add"
+            | "flickr.favorites.remove"
+            | "flickr.galleries.addPhoto"
+            | "flickr.galleries.create"
+            | "flickr.galleries.editMeta"
+            | "flickr.galleries.editPhoto"
+            | "flickr.galleries.editPhotos"
+            | "flickr.groups.join"
+            | "flickr.groups.joinRequest"
+            | "flickr.groups.leave"
+            | "flickr.groups.discuss.replies.add"
+            | "flickr.groups.discuss.replies.delete"
+            | "flickr.groups.discuss.replies.edit"
+            | "flickr.groups.discuss.topics.add"
+            | "flickr.groups.pools.add"
+            | "flickr.groups.pools.remove"
+            | "flickr.photos.addTags"
+            | "flickr.photos.removeTag"
+            | "flickr.photos.setContentType"
+            | "flickr.photos.setDates"
+            | "flickr.photos.setMeta"
+            | "flickr.photos.setPerms"
+            | "flickr.photos.setSafetyLevel"
+            | "flickr.photos.setTags"
+            | "flickr.photos.comments.addComment"
+            | "flickr.photos.comments.deleteComment"
+            | "flickr.photos.comments.editComment"
+            | "flickr.photos.geo.batchCorrectLocation"
+            | "flickr.photos.geo.correctLocation"
+            | "flickr.photos.geo.removeLocation"
+            | "flickr.photos.geo.setContext"
+            | "flickr.photos.geo.setLocation"
+            | "flickr.photos.geo.setPerms"
+            | "flickr.photos.licenses.setLicense"
+            | "flickr.photos.notes.add"
+            | "flickr.photos.notes.delete"
+            | "flickr.photos.notes.edit"
+            | "flickr.photos.people.add"
+            | "flickr.photos.people.delete"
+            | "flickr.photos.people.deleteCoords"
+            | "flickr.photos.people.editCoords"
+            | "flickr.photos.suggestions.approveSuggestion"
+            | "flickr.photos.suggestions.rejectSuggestion"
+            | "flickr.photos.suggestions.removeSuggestion"
+            | "flickr.photos.suggestions.suggestLocation"
+            | "flickr.photos.transform.rotate"
+            | "flickr.photosets.addPhoto"
+            | "flickr.photosets.create"
+            | "flickr.photosets.delete"
+            | "flickr.photosets.editMeta"
+            | "flickr.photosets.editPhotos"
+            | "flickr.photosets.orderSets"
+            | "flickr.photosets.removePhoto"
+            | "flickr.photosets.removePhotos"
+            | "flickr.photosets.reorderPhotos"
+            | "flickr.photosets.setPrimaryPhoto"
+            | "flickr.photosets.comments.addComment"
+            | "flickr.photosets.comments.deleteComment"
+            | "flickr.photosets.comments.editComment"
+            | "flickr.testimonials.addTestimonial"
+            | "flickr.testimonials.approveTestimonial"
+            | "flickr.testimonials.deleteTestimonial"
+            | "flickr.testimonials.editTestimonial" => Permission::Write,
+
+            _ => Permission::Read,
+        }
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(formatter, "{}", self.as_str())
+    }
+}
+
+/// The account a token was issued for
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Identity {
+    /// The user's ID, used as `user_id` by the API
+    pub nsid: UserId,
+    pub username: String,
+    pub fullname: String,
+}
+
 /// OAuth token
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Token {
     pub token: String,
     pub secret: String,
+    /// The access level granted when the token was issued, if known
+    #[serde(default)]
+    pub perms: Option<Permission>,
+    /// The account the token was issued for, if known
+    #[serde(default)]
+    pub user: Option<Identity>,
 }
 
 /// flickr API key
@@ -36,20 +158,24 @@ pub enum OauthTokenAnswer {
     Err(OauthErrorDescription),
 }
 
-impl Resultable<Token, String> for OauthTokenAnswer {
-    fn to_result(self) -> Result<Token, String> {
+impl Resultable<Token, Error> for OauthTokenAnswer {
+    fn to_result(self) -> Result<Token, Error> {
         match self {
             OauthTokenAnswer::Ok(OauthTokenGranted {
                 oauth_callback_confirmed: _,
                 oauth_token: token,
                 oauth_token_secret: secret,
-            }) => Ok(Token { token, secret }),
-            OauthTokenAnswer::Err(e) => Err(e.oauth_problem),
+            }) => Ok(Token {
+                token,
+                secret,
+                ..Default::default()
+            }),
+            OauthTokenAnswer::Err(e) => Err(Error::Oauth(e)),
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct OauthAccessGranted {
     pub fullname: String,
     pub username: String,
@@ -58,40 +184,51 @@ pub struct OauthAccessGranted {
     pub oauth_token_secret: String,
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct OauthTokenGranted {
     pub oauth_callback_confirmed: String,
     pub oauth_token: String,
     pub oauth_token_secret: String,
 }
 
-#[derive(Debug, Default, Deserialize)]
+/// Problem reported by an OAuth endpoint
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct OauthErrorDescription {
     pub oauth_problem: String,
+    /// The signature base string computed by the server, to compare with the local one
     #[serde(default)]
     pub debug_sbs: String,
 }
 
-impl Resultable<OauthAccessGranted, String> for OauthAccessAnswer {
-    fn to_result(self) -> Result<OauthAccessGranted, String> {
+impl Resultable<OauthAccessGranted, Error> for OauthAccessAnswer {
+    fn to_result(self) -> Result<OauthAccessGranted, Error> {
         match self {
             OauthAccessAnswer::Ok(k) => Ok(k),
-            OauthAccessAnswer::Err(e) => Err(e.oauth_problem),
+            OauthAccessAnswer::Err(e) => Err(Error::Oauth(e)),
         }
     }
 }
 
-impl Resultable<Token, String> for OauthAccessAnswer {
-    fn to_result(self) -> Result<Token, String> {
+impl Resultable<Token, Error> for OauthAccessAnswer {
+    fn to_result(self) -> Result<Token, Error> {
         match self {
             OauthAccessAnswer::Ok(OauthAccessGranted {
-                fullname: _,
-                username: _,
-                user_nsid: _,
+                fullname,
+                username,
+                user_nsid,
                 oauth_token: token,
                 oauth_token_secret: secret,
-            }) => Ok(Token { token, secret }),
-            OauthAccessAnswer::Err(e) => Err(e.oauth_problem),
+            }) => Ok(Token {
+                token,
+                secret,
+                perms: None,
+                user: Some(Identity {
+                    nsid: user_nsid.into(),
+                    username,
+                    fullname,
+                }),
+            }),
+            OauthAccessAnswer::Err(e) => Err(Error::Oauth(e)),
         }
     }
 }
@@ -99,6 +236,7 @@ impl Resultable<Token, String> for OauthAccessAnswer {
 /// The type of request that this URL expects
 ///
 /// This is used by the signature algorithm and needs to match what is done later.
+#[derive(Debug, Clone, Copy)]
 pub enum RequestTarget<'a> {
     Get(&'a str),
     Post(&'a str),
@@ -111,73 +249,586 @@ impl<'a> RequestTarget<'a> {
             RequestTarget::Post(val) => val,
         }
     }
+
+    fn method(&self) -> &'static str {
+        match self {
+            RequestTarget::Get(_) => "GET",
+            RequestTarget::Post(_) => "POST",
+        }
+    }
+}
+
+/// Percent-encodes a value as OAuth requires ([RFC 5849 section 3.6](https://datatracker.ietf.org/doc/html/rfc5849#section-3.6))
+///
+/// Every byte of the UTF-8 representation is encoded as `%XX` with uppercase hexadecimal digits,
+/// except unreserved characters: `A-Z`, `a-z`, `0-9`, `-`, `.`, `_` and `~`. Unlike form
+/// encoding, spaces become `%20` and `*` is encoded.
+pub fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
+
+/// The base string URI of a request: lowercase scheme and host, no default port, no query
+fn base_string_uri(uri: &str) -> String {
+    match url::Url::parse(uri) {
+        Ok(url) => {
+            let port = url
+                .port()
+                .map(|port| format!(":{port}"))
+                .unwrap_or_default();
+            format!(
+                "{}://{}{port}{}",
+                url.scheme(),
+                url.host_str().unwrap_or_default(),
+                url.path()
+            )
+        }
+        Err(_) => uri.to_string(),
+    }
+}
+
+/// Builds the signature base string of a request ([RFC 5849 section 3.4.1](https://datatracker.ietf.org/doc/html/rfc5849#section-3.4.1))
+///
+/// `params` are all the parameters of the request, except `oauth_signature`. They are encoded,
+/// then sorted by name and by value for names that appear more than once.
+pub fn signature_base_string<K: AsRef<str>, V: AsRef<str>>(
+    method: &str,
+    uri: &str,
+    params: &[(K, V)],
+) -> String {
+    let mut params = params
+        .iter()
+        .filter(|(k, _)| !["photo"].contains(&k.as_ref()))
+        .map(|(k, v)| (percent_encode(k.as_ref()), percent_encode(v.as_ref())))
+        .collect::<Vec<_>>();
+
+    params.sort();
+
+    let normalized = params.iter().map(|(k, v)| format!("{k}={v}")).join("&");
+
+    format!(
+        "{}&{}&{}",
+        method.to_uppercase(),
+        percent_encode(&base_string_uri(uri)),
+        percent_encode(&normalized)
+    )
+}
+
+/// Computes the HMAC-SHA1 signature of a request
+///
+/// `params` are all the parameters of the request, except `oauth_signature`. `key` is the
+/// consumer secret and the token secret (empty if there is none), each percent-encoded, joined
+/// by `&`.
+pub fn signature<K: AsRef<str>, V: AsRef<str>>(
+    method: &str,
+    uri: &str,
+    params: &[(K, V)],
+    key: &str,
+) -> String {
+    let raw = signature_base_string(method, uri, params);
+
+    let mut mac = HmacSha1::new_from_slice(key.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(raw.as_bytes());
+    BASE64.encode(&mac.finalize().into_bytes())
+}
+
+/// The key used to sign requests, from the consumer secret and token secret
+pub fn signing_key(consumer_secret: &str, token_secret: Option<&str>) -> String {
+    format!(
+        "{}&{}",
+        percent_encode(consumer_secret),
+        percent_encode(token_secret.unwrap_or_default())
+    )
+}
+
+/// Where the OAuth parameters of a request are sent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthorizationMode {
+    /// Along with the other parameters, in the query string or the form-encoded body
+    #[default]
+    Parameters,
+    /// In an `Authorization: OAuth ...` header ([RFC 5849 section 3.5.1](https://datatracker.ietf.org/doc/html/rfc5849#section-3.5.1))
+    Header,
+}
+
+/// Removes the OAuth parameters from a signed request and returns them as the value of an
+/// `Authorization` header
+pub fn authorization_header<K: AsRef<str>>(params: &mut Vec<(K, String)>) -> String {
+    let (oauth, others): (Vec<_>, Vec<_>) = params
+        .drain(..)
+        .partition(|(k, _)| k.as_ref().starts_with("oauth_"));
+    *params = others;
+
+    let fields = oauth
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", percent_encode(k.as_ref()), percent_encode(v)))
+        .join(", ");
+
+    format!("OAuth {fields}")
+}
+
+/// Source of the `oauth_timestamp` of requests
+pub trait Clock: Send + Sync {
+    /// Seconds elapsed since the Unix epoch
+    fn now(&self) -> u64;
+}
+
+/// The system's clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+}
+
+impl<F: Fn() -> u64 + Send + Sync> Clock for F {
+    fn now(&self) -> u64 {
+        self()
+    }
 }
 
-/// Prepares a request to be sent with authentication
+/// Source of the `oauth_nonce` of requests
 ///
-/// This supplements the given parameters with a signature and necessary oauth fields. This methods
-/// is dependent on the [RequestTarget] enum indicating which protocol to use as this influences
-/// the signature.
-pub fn build_request(
+/// Flickr refuses a nonce it has already seen with the same timestamp, so every call must return
+/// a different value.
+pub trait NonceSource: Send + Sync {
+    fn nonce(&self) -> String;
+}
+
+impl<F: Fn() -> String + Send + Sync> NonceSource for F {
+    fn nonce(&self) -> String {
+        self()
+    }
+}
+
+/// Random nonces of 32 alphanumeric characters, drawn from a cryptographically secure generator
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomNonce;
+
+impl NonceSource for RandomNonce {
+    fn nonce(&self) -> String {
+        use rand::distributions::{Alphanumeric, DistString};
+
+        Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+    }
+}
+
+/// Signs requests, using the given clock and nonce source
+#[derive(Clone)]
+pub struct Signer {
+    clock: Arc<dyn Clock>,
+    nonces: Arc<dyn NonceSource>,
+}
+
+impl Default for Signer {
+    fn default() -> Self {
+        Signer::new(SystemClock, RandomNonce)
+    }
+}
+
+impl Signer {
+    pub fn new(clock: impl Clock + 'static, nonces: impl NonceSource + 'static) -> Self {
+        Signer {
+            clock: Arc::new(clock),
+            nonces: Arc::new(nonces),
+        }
+    }
+
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        Signer {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    pub fn with_nonces(self, nonces: impl NonceSource + 'static) -> Self {
+        Signer {
+            nonces: Arc::new(nonces),
+            ..self
+        }
+    }
+
+    /// Prepares a request to be sent with authentication
+    ///
+    /// This supplements the given parameters with a signature and necessary oauth fields. This
+    /// methods is dependent on the [RequestTarget] enum indicating which protocol to use as this
+    /// influences the signature.
+    pub fn build_request<K: AsRef<str> + From<&'static str>>(
+        &self,
+        target: RequestTarget,
+        params: &mut Vec<(K, String)>,
+        api: &ApiKey,
+        oauth: Option<&Token>,
+    ) {
+        params.extend(vec![
+            ("oauth_consumer_key".into(), api.key.clone()),
+            ("oauth_nonce".into(), self.nonces.nonce()),
+            ("oauth_signature_method".into(), "HMAC-SHA1".to_string()),
+            ("oauth_timestamp".into(), self.clock.now().to_string()),
+            ("oauth_version".into(), "1.0".to_string()),
+        ]);
+
+        if let Some(value) = oauth {
+            params.push(("oauth_token".into(), value.token.clone()));
+        }
+        let key = signing_key(&api.secret, oauth.map(|value| value.secret.as_str()));
+
+        params.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+
+        let signature = signature(target.method(), target.uri(), params, &key);
+
+        params.push(("oauth_signature".into(), signature));
+    }
+}
+
+/// Prepares a request to be sent with authentication, with the system clock and random nonces
+///
+/// See [Signer::build_request].
+pub fn build_request<K: AsRef<str> + From<&'static str>>(
     target: RequestTarget,
-    params: &mut Vec<(&'static str, String)>,
+    params: &mut Vec<(K, String)>,
     api: &ApiKey,
     oauth: Option<&Token>,
 ) {
-    let seconds = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        .to_string();
-
-    let nonce = seconds[1..9].to_string();
-
-    params.extend(vec![
-        ("oauth_consumer_key", api.key.clone()),
-        ("oauth_nonce", nonce),
-        ("oauth_signature_method", "HMAC-SHA1".to_string()),
-        ("oauth_timestamp", seconds),
-        ("oauth_version", "1.0".to_string()),
-    ]);
-
-    let key: String;
-
-    match &oauth {
-        Some(value) => {
-            params.extend(vec![("oauth_token", value.token.clone())]);
-            key = format!("{}&{}", api.secret, value.secret)
-        }
-        None => key = format!("{}&", api.secret),
-    };
+    Signer::default().build_request(target, params, api, oauth)
+}
 
-    params.sort_by(|a, b| a.0.cmp(b.0));
+#[test]
+fn test_permission_required_by() {
+    assert_eq!(
+        Permission::required_by("flickr.photos.getInfo"),
+        Permission::Read
+    );
+    assert_eq!(
+        Permission::required_by("flickr.test.login"),
+        Permission::Read
+    );
+    assert_eq!(
+        Permission::required_by("flickr.places.placesForUser"),
+        Permission::Read
+    );
+    assert_eq!(
+        Permission::required_by("flickr.photos.setMeta"),
+        Permission::Write
+    );
+    assert_eq!(
+        Permission::required_by("flickr.photos.addTags"),
+        Permission::Write
+    );
+    assert_eq!(
+        Permission::required_by("flickr.photosets.delete"),
+        Permission::Write
+    );
+    assert_eq!(
+        Permission::required_by("flickr.photos.delete"),
+        Permission::Delete
+    );
 
-    let to_sign = params
-        .iter()
-        .filter(|(k, _)| !vec!["photo"].contains(k))
-        .map(|(a, b)| {
-            format!(
-                "{a}={}",
-                form_urlencoded::byte_serialize(&b.as_bytes()).collect::<String>(),
-            )
+    // Methods not listed are left to flickr, whatever their name looks like
+    assert_eq!(
+        Permission::required_by("flickr.photos.settleSomethingNew"),
+        Permission::Read
+    );
+
+    assert!(Permission::Read < Permission::Write);
+    assert!(Permission::Write < Permission::Delete);
+}
+
+#[test]
+fn test_access_answer_keeps_identity() {
+    let raw = "fullname=Jamal%20Fanaian&oauth_token=72157626318069415-087bfc7b5816092c&oauth_token_secret=a202d1f853ec69de&user_nsid=21207597%40N07&username=jamalfanaian";
+
+    let answer: OauthAccessAnswer = serde_urlencoded::from_str(raw).unwrap();
+    let token: Token = answer.to_result().unwrap();
+
+    assert_eq!(token.token, "72157626318069415-087bfc7b5816092c");
+    assert_eq!(
+        token.user,
+        Some(Identity {
+            nsid: "21207597@N07".into(),
+            username: "jamalfanaian".into(),
+            fullname: "Jamal Fanaian".into(),
         })
-        .join("&");
+    );
+}
+
+#[test]
+fn test_percent_encode() {
+    assert_eq!(
+        percent_encode("Ladies + Gentlemen"),
+        "Ladies%20%2B%20Gentlemen"
+    );
+    assert_eq!(
+        percent_encode("An encoded string!"),
+        "An%20encoded%20string%21"
+    );
+    assert_eq!(
+        percent_encode("Dogs, Cats & Mice"),
+        "Dogs%2C%20Cats%20%26%20Mice"
+    );
+    assert_eq!(percent_encode("a*b(c)'d"), "a%2Ab%28c%29%27d");
+    assert_eq!(percent_encode("-._~AZaz09"), "-._~AZaz09");
+    assert_eq!(percent_encode("☃"), "%E2%98%83");
+    assert_eq!(percent_encode("é"), "%C3%A9");
+}
 
-    let uri = target.uri();
-    let method = match target {
-        RequestTarget::Get(_) => "GET",
-        RequestTarget::Post(_) => "POST",
-    };
+/// [RFC 5849 section 3.4.1.1](https://datatracker.ietf.org/doc/html/rfc5849#section-3.4.1.1)
+#[test]
+fn test_base_string_rfc5849() {
+    let params = [
+        ("b5", "=%3D"),
+        ("a3", "a"),
+        ("c@", ""),
+        ("a2", "r b"),
+        ("oauth_consumer_key", "9djdj82h48djs9d2"),
+        ("oauth_token", "kkk9d7dh3k39sjv7"),
+        ("oauth_signature_method", "HMAC-SHA1"),
+        ("oauth_timestamp", "137131201"),
+        ("oauth_nonce", "7d8f3e4a"),
+        ("c2", ""),
+        ("a3", "2 q"),
+    ];
 
-    let raw = format!(
-        "{method}&{}&{}",
-        form_urlencoded::byte_serialize(&uri.as_bytes()).collect::<String>(),
-        form_urlencoded::byte_serialize(&to_sign.as_bytes()).collect::<String>()
+    assert_eq!(
+        signature_base_string("POST", "http://example.com/request", &params),
+        "POST&http%3A%2F%2Fexample.com%2Frequest&a2%3Dr%2520b%26a3%3D2%2520q\
+         %26a3%3Da%26b5%3D%253D%25253D%26c%2540%3D%26c2%3D%26oauth_consumer_\
+         key%3D9djdj82h48djs9d2%26oauth_nonce%3D7d8f3e4a%26oauth_signature_m\
+         ethod%3DHMAC-SHA1%26oauth_timestamp%3D137131201%26oauth_token%3Dkkk\
+         9d7dh3k39sjv7"
     );
+}
 
-    let mut mac = HmacSha1::new_from_slice(key.as_bytes()).expect("HMAC can take key of any size");
-    mac.update(raw.as_bytes());
-    let signature: String = BASE64.encode(&mac.finalize().into_bytes());
+/// [RFC 5849 section 3.4.1.2](https://datatracker.ietf.org/doc/html/rfc5849#section-3.4.1.2)
+#[test]
+fn test_base_string_uri() {
+    assert_eq!(
+        base_string_uri("HTTP://EXAMPLE.COM:80/r%20v/X?id=123"),
+        "http://example.com/r%20v/X"
+    );
+    assert_eq!(
+        base_string_uri("https://www.example.net:8080/?q=1"),
+        "https://www.example.net:8080/"
+    );
+}
+
+/// [OAuth Core 1.0 appendix A.5](https://oauth.net/core/1.0/#sig_base_example)
+#[test]
+fn test_signature_oauth_core() {
+    let params = [
+        ("file", "vacation.jpg"),
+        ("size", "original"),
+        ("oauth_consumer_key", "dpf43f3p2l4k3l03"),
+        ("oauth_token", "nnch734d00sl2jdk"),
+        ("oauth_signature_method", "HMAC-SHA1"),
+        ("oauth_timestamp", "1191242096"),
+        ("oauth_nonce", "kllo9940pd9333jh"),
+        ("oauth_version", "1.0"),
+    ];
+    let uri = "http://photos.example.net/photos";
+
+    assert_eq!(
+        signature_base_string("GET", uri, &params),
+        "GET&http%3A%2F%2Fphotos.example.net%2Fphotos&file%3Dvacation.jpg%26\
+         oauth_consumer_key%3Ddpf43f3p2l4k3l03%26oauth_nonce%3Dkllo9940pd9333jh%26\
+         oauth_signature_method%3DHMAC-SHA1%26oauth_timestamp%3D1191242096%26\
+         oauth_token%3Dnnch734d00sl2jdk%26oauth_version%3D1.0%26size%3Doriginal"
+    );
+    assert_eq!(
+        signature(
+            "GET",
+            uri,
+            &params,
+            &signing_key("kd94hf93k423kf44", Some("pfkkdhi9sl3r4s00"))
+        ),
+        "tR3+Ty81lMeYAr/Fid0kMTYa/WM="
+    );
+}
+
+/// Reserved characters in values, from [Twitter's documentation](https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature)
+#[test]
+fn test_signature_reserved_characters() {
+    let params = [
+        (
+            "status",
+            "Hello Ladies + Gentlemen, a signed OAuth request!",
+        ),
+        ("include_entities", "true"),
+        ("oauth_consumer_key", "xvz1evFS4wEEPTGEFPHBog"),
+        ("oauth_nonce", "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg"),
+        ("oauth_signature_method", "HMAC-SHA1"),
+        ("oauth_timestamp", "1318622958"),
+        (
+            "oauth_token",
+            "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+        ),
+        ("oauth_version", "1.0"),
+    ];
+
+    assert_eq!(
+        signature(
+            "POST",
+            "https://api.twitter.com/1/statuses/update.json",
+            &params,
+            &signing_key(
+                "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+                Some("LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE")
+            )
+        ),
+        "tnnArxj06cWHq44gCs1OSKk/jLY="
+    );
+}
+
+#[test]
+fn test_base_string_unicode() {
+    let params = [
+        ("title", "Café ☕ *Paris*"),
+        ("description", "Œuvre n°1"),
+        ("oauth_consumer_key", "key"),
+    ];
+
+    assert_eq!(
+        signature_base_string("GET", "https://api.flickr.com/services/rest/", &params),
+        "GET&https%3A%2F%2Fapi.flickr.com%2Fservices%2Frest%2F&\
+         description%3D%25C5%2592uvre%2520n%25C2%25B01%26\
+         oauth_consumer_key%3Dkey%26\
+         title%3DCaf%25C3%25A9%2520%25E2%2598%2595%2520%252AParis%252A"
+    );
+}
+
+#[test]
+fn test_base_string_skips_photo() {
+    let with_photo = [("title", "a"), ("photo", "binary")];
+    let without_photo = [("title", "a")];
+
+    assert_eq!(
+        signature_base_string(
+            "POST",
+            "https://up.flickr.com/services/upload/",
+            &with_photo
+        ),
+        signature_base_string(
+            "POST",
+            "https://up.flickr.com/services/upload/",
+            &without_photo
+        ),
+    );
+}
+
+#[test]
+fn test_signer_deterministic() {
+    struct Fixed(&'static str);
+
+    impl NonceSource for Fixed {
+        fn nonce(&self) -> String {
+            self.0.to_string()
+        }
+    }
 
-    params.push(("oauth_signature", signature));
+    impl Clock for Fixed {
+        fn now(&self) -> u64 {
+            1191242096
+        }
+    }
+
+    let signer = Signer::new(Fixed(""), Fixed("kllo9940pd9333jh"));
+    let mut params = vec![
+        ("file", "vacation.jpg".to_string()),
+        ("size", "original".to_string()),
+    ];
+    signer.build_request(
+        RequestTarget::Get("http://photos.example.net/photos"),
+        &mut params,
+        &ApiKey {
+            key: "dpf43f3p2l4k3l03".into(),
+            secret: "kd94hf93k423kf44".into(),
+        },
+        Some(&Token {
+            token: "nnch734d00sl2jdk".into(),
+            secret: "pfkkdhi9sl3r4s00".into(),
+            ..Default::default()
+        }),
+    );
+
+    assert_eq!(
+        params.last().unwrap(),
+        &(
+            "oauth_signature",
+            "tR3+Ty81lMeYAr/Fid0kMTYa/WM=".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_nonces_unique_across_threads() {
+    let signer = Signer::default();
+
+    let nonces = std::thread::scope(|scope| {
+        let workers = (0..8)
+            .map(|_| {
+                scope.spawn(|| {
+                    (0..500)
+                        .map(|_| {
+                            let mut params: Vec<(&str, String)> = vec![];
+                            signer.build_request(
+                                RequestTarget::Post("https://up.flickr.com/services/upload/"),
+                                &mut params,
+                                &ApiKey::default(),
+                                None,
+                            );
+                            params
+                                .into_iter()
+                                .find(|(k, _)| *k == "oauth_nonce")
+                                .unwrap()
+                                .1
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let unique = nonces.iter().collect::<std::collections::HashSet<_>>();
+    assert_eq!(unique.len(), nonces.len());
+    assert!(nonces
+        .iter()
+        .all(|nonce| nonce.len() == 32 && nonce.chars().all(|c| c.is_ascii_alphanumeric())));
+}
+
+#[test]
+fn test_authorization_header() {
+    let mut params = vec![
+        ("oauth_consumer_key", "key".to_string()),
+        ("title", "Ladies + Gentlemen".to_string()),
+        (
+            "oauth_signature",
+            "tR3+Ty81lMeYAr/Fid0kMTYa/WM=".to_string(),
+        ),
+    ];
+
+    assert_eq!(
+        authorization_header(&mut params),
+        r#"OAuth oauth_consumer_key="key", oauth_signature="tR3%2BTy81lMeYAr%2FFid0kMTYa%2FWM%3D""#
+    );
+    assert_eq!(params, vec![("title", "Ladies + Gentlemen".to_string())]);
 }
@@ -0,0 +1,203 @@
+use crate::*;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How many requests a client may send over time
+///
+/// Requests are let through as long as tokens are available in a bucket holding up to `burst`
+/// tokens, refilled at `requests` per `per`. Once it is empty, requests wait for the next token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    /// Number of requests allowed over `per`
+    pub requests: u32,
+    pub per: Duration,
+    /// Number of requests that can be sent at once after a quiet period
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    /// Flickr's quota of 3600 requests per hour per API key, with bursts of up to 60 requests
+    fn default() -> Self {
+        RateLimit {
+            requests: 3600,
+            per: Duration::from_secs(3600),
+            burst: 60,
+        }
+    }
+}
+
+impl RateLimit {
+    /// Time needed to earn a token
+    fn interval(&self) -> Duration {
+        self.per / self.requests.max(1)
+    }
+}
+
+/// How much a client was slowed down by its [RateLimit]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimitStats {
+    /// Requests let through
+    pub requests: u64,
+    /// Requests that had to wait for a token
+    pub delayed: u64,
+    /// Time spent waiting by all requests
+    pub total_delay: Duration,
+    /// Longest time a request waited
+    pub max_delay: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket shared by all the clones of a client
+pub(crate) struct RateLimiter {
+    limit: Option<RateLimit>,
+    /// Held while waiting for a token, so requests are let through in order
+    bucket: tokio::sync::Mutex<Bucket>,
+    stats: Mutex<RateLimitStats>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: Option<RateLimit>) -> Self {
+        RateLimiter {
+            limit,
+            bucket: tokio::sync::Mutex::new(Bucket {
+                tokens: limit.map(|limit| limit.burst as f64).unwrap_or_default(),
+                updated: Instant::now(),
+            }),
+            stats: Mutex::new(RateLimitStats::default()),
+        }
+    }
+
+    /// Wait until a request can be sent
+    pub(crate) async fn acquire(&self) {
+        let Some(limit) = self.limit else {
+            self.stats.lock().unwrap().requests += 1;
+            return;
+        };
+
+        let start = Instant::now();
+        let mut bucket = self.bucket.lock().await;
+
+        let refill = |bucket: &mut Bucket| {
+            let now = Instant::now();
+            let earned =
+                now.duration_since(bucket.updated).as_secs_f64() / limit.interval().as_secs_f64();
+            bucket.tokens = (bucket.tokens + earned).min(limit.burst.max(1) as f64);
+            bucket.updated = now;
+        };
+
+        refill(&mut bucket);
+        if bucket.tokens < 1.0 {
+            let missing = limit.interval().mul_f64(1.0 - bucket.tokens);
+            tokio::time::sleep(missing).await;
+            refill(&mut bucket);
+        }
+        bucket.tokens -= 1.0;
+        drop(bucket);
+
+        let delay = start.elapsed();
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        if !delay.is_zero() {
+            log::debug!("Request delayed by {delay:?} to respect the rate limit");
+            stats.delayed += 1;
+            stats.total_delay += delay;
+            stats.max_delay = stats.max_delay.max(delay);
+        }
+    }
+
+    pub(crate) fn limit(&self) -> Option<RateLimit> {
+        self.limit
+    }
+
+    pub(crate) fn stats(&self) -> RateLimitStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl FlickrAPI {
+    /// The rate limit requests are held to, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.data.limiter.limit()
+    }
+
+    /// How much requests were slowed down by the rate limit, over all the clones of this client
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.data.limiter.stats()
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limiter_bucket() {
+    let limiter = RateLimiter::new(Some(RateLimit {
+        requests: 1,
+        per: Duration::from_secs(1),
+        burst: 2,
+    }));
+    let start = Instant::now();
+
+    for _ in 0..5 {
+        limiter.acquire().await;
+    }
+
+    assert_eq!(start.elapsed(), Duration::from_secs(3));
+    assert_eq!(
+        limiter.stats(),
+        RateLimitStats {
+            requests: 5,
+            delayed: 3,
+            total_delay: Duration::from_secs(3),
+            max_delay: Duration::from_secs(1),
+        }
+    );
+
+    // The bucket refills while idle, up to the burst size
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    let start = Instant::now();
+    limiter.acquire().await;
+    limiter.acquire().await;
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    limiter.acquire().await;
+    assert_eq!(start.elapsed(), Duration::from_secs(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_shared_by_clones() {
+    let client = FlickrAPI::builder(ApiKey::default())
+        .rate_limit(RateLimit {
+            requests: 10,
+            per: Duration::from_secs(1),
+            burst: 1,
+        })
+        .build();
+    let start = Instant::now();
+
+    let tasks = (0..10)
+        .map(|_| {
+            let client = client.clone().with_token(OauthToken::default());
+            tokio::spawn(async move { client.data.limiter.acquire().await })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(start.elapsed(), Duration::from_millis(900));
+    let stats = client.rate_limit_stats();
+    assert_eq!(stats.requests, 10);
+    assert_eq!(stats.delayed, 9);
+    assert_eq!(stats.max_delay, Duration::from_millis(900));
+
+    let unlimited = FlickrAPI::builder(ApiKey::default())
+        .rate_limit(None)
+        .build();
+    assert_eq!(unlimited.rate_limit(), None);
+    assert_eq!(
+        FlickrAPI::new(ApiKey::default()).rate_limit(),
+        Some(RateLimit::default())
+    );
+}
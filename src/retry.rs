@@ -0,0 +1,208 @@
+use crate::*;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// When and how often failed requests are sent again
+///
+/// Each attempt is signed anew, with a fresh nonce and timestamp. The delay before attempt `n + 1`
+/// is `base_delay * 2^(n - 1)`, capped at `max_delay`.
+///
+/// Requests that change data, such as uploads and methods needing more than [Permission::Read],
+/// are not idempotent: flickr may have carried them out even if the answer was lost. They are only
+/// retried when flickr could not have received them (the connection failed) or explicitly
+/// refused them with one of `retryable_codes`, unless `retry_non_idempotent` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of attempts, including the first one. `1` disables retrying
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_delay: Duration,
+    /// Randomize each delay between half and all of its value, to spread clients apart
+    pub jitter: bool,
+    /// Error codes flickr returns for requests that may succeed later
+    pub retryable_codes: Vec<ErrorCode>,
+    /// Also retry requests that change data after failures where they may have gone through
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retryable_codes: vec![ErrorCode::ServiceUnavailable],
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, the behaviour of clients built without a policy
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a request failing with `error` is worth sending again
+    pub fn is_retryable(&self, error: &Error, idempotent: bool) -> bool {
+        let idempotent = idempotent || self.retry_non_idempotent;
+
+        match error {
+            Error::Transport(e) if e.is_connect() => true,
+            Error::Transport(e) => idempotent && (e.is_timeout() || e.is_request() || e.is_body()),
+            Error::Io(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => true,
+            Error::Io(e) => {
+                use std::io::ErrorKind::*;
+                idempotent
+                    && matches!(
+                        e.kind(),
+                        ConnectionReset | ConnectionAborted | TimedOut | UnexpectedEof
+                    )
+            }
+            Error::Http { status, .. } => idempotent && (*status >= 500 || *status == 429),
+            Error::Api(e) => self.retryable_codes.contains(&e.code),
+            _ => false,
+        }
+    }
+
+    /// How long to wait after the given failed attempt, counting from 1, before jitter
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    fn jittered_delay(&self, attempt: u32) -> Duration {
+        let delay = self.delay(attempt);
+
+        match self.jitter {
+            true => delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0)),
+            false => delay,
+        }
+    }
+
+    /// Run `attempt` until it succeeds, fails with an error that is not worth retrying, or the
+    /// attempts are exhausted
+    pub(crate) async fn run<T, F, Fut>(&self, idempotent: bool, mut attempt: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut count = 1;
+
+        loop {
+            match attempt().await {
+                Err(e) if count < self.max_attempts && self.is_retryable(&e, idempotent) => {
+                    let delay = self.jittered_delay(count);
+                    log::warn!("Attempt {count} failed: {e}. Retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    count += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_retry_delay() {
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        ..Default::default()
+    };
+
+    assert_eq!(policy.delay(1), Duration::from_millis(100));
+    assert_eq!(policy.delay(2), Duration::from_millis(200));
+    assert_eq!(policy.delay(4), Duration::from_millis(800));
+    assert_eq!(policy.delay(5), Duration::from_secs(1));
+    assert_eq!(policy.delay(100), Duration::from_secs(1));
+
+    for attempt in 1..10 {
+        let delay = policy.jittered_delay(attempt);
+        assert!(delay >= policy.delay(attempt) / 2 && delay <= policy.delay(attempt));
+    }
+}
+
+#[test]
+fn test_retryable_errors() {
+    let policy = RetryPolicy::default();
+    let unavailable = Error::Api(FlickrError {
+        stat: "fail".into(),
+        code: ErrorCode::ServiceUnavailable,
+        message: "Service currently unavailable".into(),
+    });
+    let bad_gateway = Error::Http {
+        status: 502,
+        body: String::new(),
+    };
+    let not_found = Error::Api(FlickrError {
+        stat: "fail".into(),
+        code: ErrorCode::MethodSpecific(1),
+        message: "Photo not found".into(),
+    });
+
+    assert!(policy.is_retryable(&unavailable, true));
+    assert!(policy.is_retryable(&unavailable, false));
+    assert!(policy.is_retryable(&bad_gateway, true));
+    assert!(!policy.is_retryable(&bad_gateway, false));
+    assert!(!policy.is_retryable(&not_found, true));
+    assert!(!policy.is_retryable(&Error::Login(LoginError::Timeout), true));
+
+    let policy = RetryPolicy {
+        retry_non_idempotent: true,
+        ..Default::default()
+    };
+    assert!(policy.is_retryable(&bad_gateway, false));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_retry_transient_failures() {
+    use crate::mock::{test_key, MockFailure, MockPhoto, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(vec![]));
+    let client = FlickrAPI::builder(test_key())
+        .endpoints(server.endpoints())
+        .token(server.issue_token(Permission::Write))
+        .retry(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        })
+        .build();
+
+    // Every attempt is signed again, or the server would refuse the reused nonce
+    server.fail_next(1, MockFailure::ServiceUnavailable);
+    server.fail_next(1, MockFailure::Status(503));
+    client.photos().get_info(&id, None).await.unwrap();
+
+    server.fail_next(3, MockFailure::ServiceUnavailable);
+    match client.photos().get_info(&id, None).await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::ServiceUnavailable),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+
+    // The upload may have gone through, so it is not sent again
+    server.fail_next(1, MockFailure::Status(503));
+    match client.photos().upload(b"photo", None).await {
+        Err(Error::Http { status, .. }) => assert_eq!(status, 503),
+        other => panic!("Unexpected answer: {other:?}"),
+    }
+
+    // Flickr refused it, so it can be sent again
+    server.fail_next(1, MockFailure::ServiceUnavailable);
+    client.photos().upload(b"photo", None).await.unwrap();
+    assert_eq!(server.photos().len(), 2);
+}
@@ -6,26 +6,12 @@ struct TestLoginAnswerSuccess {
     user: UserData,
 }
 
-#[derive(Deserialize, Debug, Hash)]
-#[serde(untagged)]
-enum TestLoginAnswer {
-    Ok(TestLoginAnswerSuccess),
-    Err(FlickrError),
-}
-
-impl Resultable<UserData, Box<dyn Error>> for TestLoginAnswer {
-    fn to_result(self) -> Result<UserData, Box<dyn Error>> {
-        match self {
-            TestLoginAnswer::Ok(TestLoginAnswerSuccess { stat: _, user }) => Ok(user),
-            TestLoginAnswer::Err(e) => Err(Box::new(e)),
-        }
-    }
-}
-
 /// User information as returned by flickr
-#[derive(Deserialize, Debug, Hash)]
+///
+/// Serializes with `username` as a plain string rather than flickr's `{"_content": ...}` wrapper
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct UserData {
-    pub id: String,
+    pub id: UserId,
     #[serde(deserialize_with = "deserialize_content")]
     pub username: String,
 }
@@ -33,24 +19,25 @@ pub struct UserData {
 impl TestRequestBuilder {
     /// [flickr.test.login](https://www.flickr.com/services/api/flickr.test.login.html)
     /// endpoint. Check the login information
-    pub async fn login(&self) -> Result<UserData, Box<dyn Error>> {
-        let mut params = vec![
-            ("method", "flickr.test.login".into()),
-            ("format", "json".into()),
-            ("nojsoncallback", "1".into()),
-        ];
-
-        oauth::build_request(
-            oauth::RequestTarget::Get(URL_API),
-            &mut params,
-            &self.handle.key,
-            self.handle.token.as_ref(),
-        );
-
-        let url = reqwest::Url::parse_with_params(URL_API, &params)?;
-        let request = self.handle.client.get(url).send().await?;
-        let login: TestLoginAnswer = request.json().await?;
+    pub async fn login(&self) -> Result<UserData, Error> {
+        let answer: TestLoginAnswerSuccess =
+            self.handle.call_as("flickr.test.login", vec![]).await?;
 
-        login.to_result()
+        Ok(answer.user)
     }
 }
+
+#[test]
+fn test_user_data_round_trip() {
+    let answer: TestLoginAnswerSuccess = serde_json::from_str(
+        r#"{"user":{"id":"12345678@N00","username":{"_content":"spoutnik"}},"stat":"ok"}"#,
+    )
+    .unwrap();
+
+    let serialized = serde_json::to_string(&answer.user).unwrap();
+    assert_eq!(serialized, r#"{"id":"12345678@N00","username":"spoutnik"}"#);
+    assert_eq!(
+        serde_json::from_str::<UserData>(&serialized).unwrap(),
+        answer.user
+    );
+}
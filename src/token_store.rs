@@ -0,0 +1,281 @@
+use crate::*;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Storage for an OAuth token between runs, used by [FlickrAPI::login_or_restore]
+pub trait TokenStore: Send + Sync {
+    /// The stored token, if any
+    fn load(&self) -> Result<Option<OauthToken>, Error>;
+
+    fn save(&self, token: &OauthToken) -> Result<(), Error>;
+
+    /// Forget the stored token
+    fn clear(&self) -> Result<(), Error>;
+}
+
+/// Keeps the token in memory, for tests or short-lived processes
+#[derive(Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<OauthToken>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new(token: Option<OauthToken>) -> Self {
+        MemoryTokenStore {
+            token: Mutex::new(token),
+        }
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Result<Option<OauthToken>, Error> {
+        Ok(self.token.lock().unwrap().clone())
+    }
+
+    fn save(&self, token: &OauthToken) -> Result<(), Error> {
+        *self.token.lock().unwrap() = Some(token.clone());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        *self.token.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+/// Keeps the token as JSON in a file only readable by the current user
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileTokenStore { path: path.into() }
+    }
+
+    /// Store the token in `flickr-api/token.json` under the user's configuration directory:
+    /// `$XDG_CONFIG_HOME`, or `~/.config` if unset
+    pub fn default_location() -> Result<Self, Error> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "no configuration directory: neither XDG_CONFIG_HOME nor HOME are set",
+                )
+            })?;
+
+        Ok(FileTokenStore::new(
+            config.join("flickr-api").join("token.json"),
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Result<Option<OauthToken>, Error> {
+        let body = match std::fs::read_to_string(&self.path) {
+            Ok(body) => body,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        serde_json::from_str(&body)
+            .map(Some)
+            .map_err(|source| Error::Json { source, body })
+    }
+
+    fn save(&self, token: &OauthToken) -> Result<(), Error> {
+        use std::io::Write;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first so a crash never leaves a truncated token behind
+        let temporary = self.path.with_extension("json.tmp");
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&temporary)?;
+        #[cfg(unix)]
+        std::fs::set_permissions(
+            &temporary,
+            std::os::unix::fs::PermissionsExt::from_mode(0o600),
+        )?;
+        file.write_all(&serde_json::to_vec_pretty(token).expect("tokens serialize to JSON"))?;
+        file.sync_all()?;
+
+        std::fs::rename(&temporary, &self.path)?;
+
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FlickrAPI {
+    /// Reuse the token kept in `store` if flickr still accepts it, or log in interactively with
+    /// [FlickrAPI::login] and save the new token
+    ///
+    /// Only a token flickr refuses leads to logging in again. Other failures, such as flickr being
+    /// unavailable, are returned and the stored token is left untouched.
+    pub async fn login_or_restore(self, store: &dyn TokenStore) -> Result<Self, Error> {
+        if let Some(token) = store.load()? {
+            let client = self.clone().with_token(token.clone());
+
+            match client.auth().check_token().await {
+                // Refresh what flickr knows about the token
+                Ok(check) => {
+                    let token = OauthToken {
+                        perms: Some(check.perms),
+                        user: Some(check.user),
+                        ..token
+                    };
+                    store.save(&token)?;
+
+                    return Ok(self.with_token(token));
+                }
+                // The token was refused, fall back to logging in
+                Err(Error::Api(e))
+                    if matches!(
+                        e.code,
+                        ErrorCode::InvalidAuthToken | ErrorCode::InvalidSignature
+                    ) =>
+                {
+                    log::info!("Stored token is no longer valid: {e}")
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let client = self.login().await?;
+
+        if let Some(token) = client.token() {
+            store.save(&token)?;
+        }
+
+        Ok(client)
+    }
+}
+
+#[test]
+fn test_memory_store() {
+    let store = MemoryTokenStore::default();
+    assert!(store.load().unwrap().is_none());
+
+    store
+        .save(&OauthToken {
+            token: "token".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(store.load().unwrap().unwrap().token, "token");
+
+    store.clear().unwrap();
+    assert!(store.load().unwrap().is_none());
+}
+
+#[test]
+fn test_file_store() {
+    let directory = std::env::temp_dir().join(format!("flickr-api-test-{}", std::process::id()));
+    let store = FileTokenStore::new(directory.join("nested").join("token.json"));
+    assert!(store.load().unwrap().is_none());
+
+    let token = OauthToken {
+        token: "72157720000000000-0123456789abcdef".into(),
+        secret: "fedcba9876543210".into(),
+        perms: Some(Permission::Write),
+        user: Some(Identity {
+            nsid: "12345678@N00".into(),
+            username: "itsame".into(),
+            fullname: "It's Me".into(),
+        }),
+    };
+    store.save(&token).unwrap();
+
+    let loaded = store.load().unwrap().unwrap();
+    assert_eq!(loaded.token, token.token);
+    assert_eq!(loaded.secret, token.secret);
+    assert_eq!(loaded.perms, token.perms);
+    assert_eq!(loaded.user, token.user);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(store.path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    store.clear().unwrap();
+    assert!(store.load().unwrap().is_none());
+    store.clear().unwrap();
+
+    std::fs::remove_dir_all(directory).ok();
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_restores_stored_token() {
+    use crate::mock::{test_key, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let token = server.issue_token(Permission::Read);
+    let store = MemoryTokenStore::new(Some(OauthToken {
+        token: token.token,
+        secret: token.secret,
+        ..Default::default()
+    }));
+
+    let client = server.client().login_or_restore(&store).await.unwrap();
+    let user = client.test().login().await.unwrap();
+
+    assert_eq!(user.id, server.user().nsid);
+    assert_eq!(client.user_id(), Some(&user.id));
+    assert_eq!(client.token().unwrap().perms, Some(Permission::Read));
+
+    // The refreshed details are saved
+    let saved = store.load().unwrap().unwrap();
+    assert_eq!(saved.perms, Some(Permission::Read));
+    assert_eq!(saved.user.unwrap().nsid, user.id);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_login_restore_transient_failure() {
+    use crate::mock::{test_key, MockFailure, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let token = server.issue_token(Permission::Read);
+    let store = MemoryTokenStore::new(Some(OauthToken {
+        token: token.token.clone(),
+        secret: token.secret.clone(),
+        ..Default::default()
+    }));
+
+    // Flickr being unavailable does not mean the token is invalid
+    server.fail_next(1, MockFailure::ServiceUnavailable);
+    match server.client().login_or_restore(&store).await {
+        Err(Error::Api(e)) => assert_eq!(e.code, ErrorCode::ServiceUnavailable),
+        other => panic!("Unexpected answer: {:?}", other.map(|_| ())),
+    }
+
+    let kept = store.load().unwrap().unwrap();
+    assert_eq!(kept.token, token.token);
+    assert!(kept.perms.is_none());
+}
@@ -0,0 +1,284 @@
+use crate::*;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A request ready to be sent, already signed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpRequest {
+    /// `GET` or `POST`
+    pub method: String,
+    /// Full URL, including the query string of `GET` requests
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: &str, url: &str) -> Self {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The value of the first header called `name`, ignoring case
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Send `params` as a form-encoded body
+    pub(crate) fn form<K: AsRef<str>, V: AsRef<str>>(self, params: &[(K, V)]) -> Self {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params.iter().map(|(k, v)| (k.as_ref(), v.as_ref())))
+            .finish();
+
+        HttpRequest {
+            body: body.into_bytes(),
+            ..self.header("Content-Type", "application/x-www-form-urlencoded")
+        }
+    }
+
+    /// Send `fields` and a file called `filename` as a `multipart/form-data` body
+    pub(crate) fn multipart<K: AsRef<str>, V: AsRef<str>>(
+        self,
+        fields: &[(K, V)],
+        file_field: &str,
+        filename: &str,
+        data: &[u8],
+    ) -> Self {
+        use rand::distributions::{Alphanumeric, DistString};
+
+        let boundary = Alphanumeric.sample_string(&mut rand::thread_rng(), 32);
+        let escape = |value: &str| {
+            value
+                .replace('"', "%22")
+                .replace('\r', "%0D")
+                .replace('\n', "%0A")
+        };
+
+        let mut body = vec![];
+        for (k, v) in fields {
+            body.extend(
+                format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    escape(k.as_ref()),
+                    v.as_ref()
+                )
+                .bytes(),
+            );
+        }
+        body.extend(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n",
+                escape(file_field),
+                escape(filename)
+            )
+            .bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend(format!("\r\n--{boundary}--\r\n").bytes());
+
+        HttpRequest {
+            body,
+            ..self.header(
+                "Content-Type",
+                &format!("multipart/form-data; boundary={boundary}"),
+            )
+        }
+    }
+}
+
+/// The answer to an [HttpRequest]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The value of the first header called `name`, ignoring case
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// The body, with invalid UTF-8 replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<HttpResponse, Error>> + Send + Sync + 'a>>;
+
+/// Sends the requests of a client
+///
+/// The default, [ReqwestTransport], sends them over the network. Other implementations can
+/// answer with canned responses in tests. Network failures should be reported as
+/// [Error::Transport] or [Error::Io] so [RetryPolicy] can tell them apart.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+        (**self).send(request)
+    }
+}
+
+/// Sends requests with a [reqwest::Client]
+#[derive(Debug, Default, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let method = reqwest::Method::from_bytes(request.method.as_bytes())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+            let response = request
+                .headers
+                .iter()
+                .fold(
+                    self.client.request(method, &request.url),
+                    |builder, (k, v)| builder.header(k, v),
+                )
+                .body(request.body)
+                .send()
+                .await?;
+
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.to_string(),
+                        String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                    )
+                })
+                .collect();
+            let body = response.bytes().await?.to_vec();
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+#[test]
+fn test_form_body() {
+    let request = HttpRequest::new("POST", "https://api.flickr.com/services/rest/")
+        .form(&[("title", "Dogs, Cats & Mice"), ("tags", "a+b")]);
+
+    assert_eq!(
+        request.header_value("content-type"),
+        Some("application/x-www-form-urlencoded")
+    );
+    assert_eq!(request.body, b"title=Dogs%2C+Cats+%26+Mice&tags=a%2Bb");
+}
+
+#[test]
+fn test_multipart_body() {
+    let request = HttpRequest::new("POST", "https://up.flickr.com/services/upload/").multipart(
+        &[("oauth_token", "token")],
+        "photo",
+        "holidays.jpg",
+        b"data",
+    );
+
+    let content_type = request.header_value("Content-Type").unwrap().to_string();
+    let boundary = content_type
+        .strip_prefix("multipart/form-data; boundary=")
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(request.body).unwrap(),
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"oauth_token\"\r\n\r\ntoken\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"holidays.jpg\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\ndata\r\n\
+             --{boundary}--\r\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_fake_transport() {
+    use std::sync::Mutex;
+
+    /// Refuses the first connection, then answers every request with `flickr.test.login`
+    #[derive(Default)]
+    struct Fake {
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl Transport for Fake {
+        fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(request);
+            let first = requests.len() == 1;
+
+            Box::pin(async move {
+                if first {
+                    return Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into());
+                }
+
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: vec![],
+                    body: br#"{"user":{"id":"12345678@N00","username":{"_content":"fake"}},"stat":"ok"}"#
+                        .to_vec(),
+                })
+            })
+        }
+    }
+
+    let fake = Arc::new(Fake::default());
+    let client = FlickrAPI::builder(ApiKey::default())
+        .transport(fake.clone())
+        .retry(RetryPolicy {
+            base_delay: std::time::Duration::from_millis(1),
+            ..Default::default()
+        })
+        .build();
+
+    let user = client.test().login().await.unwrap();
+    assert_eq!(user.username, "fake");
+
+    let requests = fake.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].method, "GET");
+    assert!(requests[1].url.starts_with(URL_API));
+    assert!(requests[1].url.contains("method=flickr.test.login"));
+    assert_ne!(requests[0].url, requests[1].url, "retries are signed again");
+}
@@ -1,5 +1,4 @@
 use crate::*;
-use reqwest::multipart::{Form, Part};
 use tokio::fs::read;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -20,25 +19,29 @@ enum UploadXMLPayload {
     },
 
     #[serde(rename = "err")]
-    Err { code: String, msg: String },
+    Err {
+        #[serde(deserialize_with = "de::u32")]
+        code: u32,
+        msg: String,
+    },
 }
 
-impl UploadXMLAnswer {
-    fn to_result(self) -> Result<String, FlickrError> {
+impl Resultable<String, Error> for UploadXMLAnswer {
+    fn to_result(self) -> Result<String, Error> {
         match self.content {
             UploadXMLPayload::PhotoId { value } => Ok(value),
-            UploadXMLPayload::Err { code, msg } => Err(FlickrError {
+            UploadXMLPayload::Err { code, msg } => Err(Error::Api(FlickrError {
                 stat: self.stat,
-                code: code.parse().unwrap_or(0),
+                code: code.into(),
                 message: msg,
-            }),
+            })),
         }
     }
 }
 
 impl PhotoRequestBuilder {
     /// Access the "special" upload API and upload a photo from a given path
-    pub async fn upload_from_path(&self, path: &std::path::Path) -> Result<String, Box<dyn Error>> {
+    pub async fn upload_from_path(&self, path: &std::path::Path) -> Result<PhotoId, Error> {
         self.upload(
             &read(path).await?,
             Some(String::from(
@@ -51,43 +54,51 @@ impl PhotoRequestBuilder {
     }
 
     /// Access the "special" upload API and upload a photo from its contents
-    pub async fn upload(
-        &self,
-        photo: &[u8],
-        filename: Option<String>,
-    ) -> Result<String, Box<dyn Error>> {
-        let mut params = vec![];
-        oauth::build_request(
-            oauth::RequestTarget::Post(URL_UPLOAD),
-            &mut params,
-            &self.handle.key,
-            self.handle.token.as_ref(),
-        );
+    ///
+    /// Uploads are not idempotent: they are only retried when flickr could not have received
+    /// them, see [RetryPolicy].
+    pub async fn upload(&self, photo: &[u8], filename: Option<String>) -> Result<PhotoId, Error> {
+        self.handle.check_permission(Permission::Write)?;
 
         // Filename is apparently required and request will fail if not set
-        let photo_part =
-            Part::bytes(Vec::from(photo)).file_name(filename.unwrap_or("unknown".to_string()));
+        let filename = filename.unwrap_or("unknown".to_string());
+
+        self.handle
+            .retry
+            .run(false, || self.send_upload(photo, &filename))
+            .await
+            .map(PhotoId::from)
+    }
+
+    /// Sign and send a single upload request
+    async fn send_upload(&self, photo: &[u8], filename: &str) -> Result<String, Error> {
+        self.handle.limiter.acquire().await;
 
-        let form = params
-            .into_iter()
-            .fold(Form::new(), |form, (k, v)| form.text(k, v))
-            .part("photo", photo_part);
+        let (params, header) = self.handle.sign::<&str>(
+            oauth::RequestTarget::Post(&self.handle.endpoints.upload),
+            vec![],
+            self.handle.token.as_ref(),
+        );
 
-        let request = self
+        let mut request = self
             .handle
-            .client
-            .post(URL_UPLOAD)
-            .multipart(form)
-            .send()
-            .await?;
+            .request("POST", &self.handle.endpoints.upload)
+            .multipart(&params, "photo", filename, photo);
+        if let Some(header) = header {
+            request = request.header("Authorization", &header);
+        }
+        let response = self.handle.send(request).await?;
 
-        let text = request.text().await?;
+        let status = response.status;
+        let text = response.text();
 
         log::trace!("Upload response: {:?}", text);
 
-        let id = serde_xml_rs::from_str::<UploadXMLAnswer>(&text)?.to_result()?;
-
-        Ok(id)
+        match serde_xml_rs::from_str::<UploadXMLAnswer>(&text) {
+            Ok(answer) => answer.to_result(),
+            Err(_) if !response.is_success() => Err(Error::Http { status, body: text }),
+            Err(source) => Err(Error::Xml { source, body: text }),
+        }
     }
 }
 
@@ -102,7 +113,7 @@ fn test_upload_answer_error() {
         UploadXMLAnswer {
             stat: "fail".to_string(),
             content: UploadXMLPayload::Err {
-                code: "5".to_string(),
+                code: 5,
                 msg: "Filetype was not recognised".to_string()
             }
         }
@@ -125,3 +136,10 @@ fn test_upload_answer_ok() {
         }
     );
 }
+
+#[test]
+fn test_upload_answer_invalid_code() {
+    let anwser = r#"<?xml version="1.0" encoding="utf-8" ?><rsp stat="fail"><err code="five" msg="Filetype was not recognised"/></rsp>"#;
+
+    assert!(serde_xml_rs::from_str::<UploadXMLAnswer>(anwser).is_err());
+}
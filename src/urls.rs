@@ -0,0 +1,539 @@
+//! Links to flickr pages and static images, and the `flic.kr` short links
+//!
+//! Short links encode numeric IDs in base 58, without the characters `0`, `O`, `I` and `l`, and
+//! with lowercase letters before uppercase ones: `https://flic.kr/p/2poHDJE` is photo
+//! `53421987654`.
+use crate::get_info::{Owner, PhotoInfo};
+use crate::*;
+use std::str::FromStr;
+
+static ALPHABET: &[u8] = b"123456789abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ";
+
+static URL_PAGES: &str = "https://www.flickr.com";
+static URL_SHORT: &str = "https://flic.kr";
+static URL_STATIC: &str = "https://live.staticflickr.com";
+
+/// Encode an ID the way short links do
+pub fn encode_base58(mut value: u64) -> String {
+    let mut digits = vec![];
+
+    loop {
+        digits.push(ALPHABET[(value % 58) as usize]);
+        value /= 58;
+        if value == 0 {
+            break;
+        }
+    }
+
+    digits.iter().rev().map(|&digit| digit as char).collect()
+}
+
+/// Decode the ID of a short link. Returns `None` for invalid characters or overflows
+pub fn decode_base58(encoded: &str) -> Option<u64> {
+    if encoded.is_empty() {
+        return None;
+    }
+
+    encoded.bytes().try_fold(0u64, |value, byte| {
+        let digit = ALPHABET.iter().position(|&c| c == byte)?;
+        value.checked_mul(58)?.checked_add(digit as u64)
+    })
+}
+
+/// Either the ID of an account or the alias chosen by its owner, as found in page URLs
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NameOrId<T> {
+    Id(T),
+    Alias(String),
+}
+
+impl<T: FromStr> NameOrId<T> {
+    fn parse(value: &str) -> Self {
+        match value.parse() {
+            Ok(id) => NameOrId::Id(id),
+            Err(_) => NameOrId::Alias(value.to_string()),
+        }
+    }
+}
+
+impl<T: AsRef<str>> NameOrId<T> {
+    pub fn as_str(&self) -> &str {
+        match self {
+            NameOrId::Id(id) => id.as_ref(),
+            NameOrId::Alias(alias) => alias,
+        }
+    }
+}
+
+/// What a flickr URL points to, see [parse]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FlickrUrl {
+    /// A photo page, short link or static image. The owner is unknown for short links and
+    /// images
+    Photo {
+        owner: Option<NameOrId<UserId>>,
+        id: PhotoId,
+    },
+    /// The photostream of a user
+    User(NameOrId<UserId>),
+    /// An album, also called photoset. The owner is unknown for short links
+    Album {
+        owner: Option<NameOrId<UserId>>,
+        id: PhotosetId,
+    },
+    Gallery {
+        owner: NameOrId<UserId>,
+        id: GalleryId,
+    },
+    Group(NameOrId<GroupId>),
+}
+
+/// Find out what a flickr URL points to
+///
+/// Understands photo, photostream, album, gallery and group pages, `flic.kr` short links and
+/// static image URLs. The scheme may be omitted. Returns `None` for other URLs.
+pub fn parse(url: &str) -> Option<FlickrUrl> {
+    let url = match url::Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => url::Url::parse(&format!("https://{url}")).ok()?,
+    };
+    let host = url.host_str()?.to_ascii_lowercase();
+    let segments = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    let short_id = |encoded: &str| decode_base58(encoded).map(|id| id.to_string());
+
+    match (host.as_str(), segments.as_slice()) {
+        ("flic.kr", ["p", encoded]) => Some(FlickrUrl::Photo {
+            owner: None,
+            id: short_id(encoded)?.into(),
+        }),
+        ("flic.kr", ["s", encoded]) => Some(FlickrUrl::Album {
+            owner: None,
+            id: short_id(encoded)?.into(),
+        }),
+        (host, [.., file]) if host.ends_with(".staticflickr.com") => {
+            let id = file.split('_').next()?.parse().ok()?;
+            Some(FlickrUrl::Photo { owner: None, id })
+        }
+        ("flickr.com" | "www.flickr.com" | "m.flickr.com" | "secure.flickr.com", path) => {
+            parse_page(path)
+        }
+        _ => None,
+    }
+}
+
+fn parse_page(path: &[&str]) -> Option<FlickrUrl> {
+    match path {
+        ["photos", owner, "albums" | "sets", id, ..] => Some(FlickrUrl::Album {
+            owner: Some(NameOrId::parse(owner)),
+            id: id.parse().ok()?,
+        }),
+        ["photos", owner, "galleries", id, ..] => Some(FlickrUrl::Gallery {
+            owner: NameOrId::parse(owner),
+            id: id.parse().ok()?,
+        }),
+        ["photos", owner, id, ..] if id.parse::<PhotoId>().is_ok() => Some(FlickrUrl::Photo {
+            owner: Some(NameOrId::parse(owner)),
+            id: id.parse().ok()?,
+        }),
+        ["photos", owner, ..] | ["people", owner, ..] => {
+            Some(FlickrUrl::User(NameOrId::parse(owner)))
+        }
+        ["groups", group, ..] => Some(FlickrUrl::Group(NameOrId::parse(group))),
+        _ => None,
+    }
+}
+
+impl PhotoId {
+    /// `https://flic.kr/p/...` link to the photo, if the ID is numeric
+    pub fn short_url(&self) -> Option<String> {
+        let id = self.as_str().parse().ok()?;
+        Some(format!("{URL_SHORT}/p/{}", encode_base58(id)))
+    }
+}
+
+impl PhotosetId {
+    /// `https://flic.kr/s/...` link to the album, if the ID is numeric
+    pub fn short_url(&self) -> Option<String> {
+        let id = self.as_str().parse().ok()?;
+        Some(format!("{URL_SHORT}/s/{}", encode_base58(id)))
+    }
+}
+
+impl Owner {
+    /// The alias of the user if they chose one, their ID otherwise
+    pub fn path(&self) -> &str {
+        self.path_alias
+            .as_deref()
+            .filter(|alias| !alias.is_empty())
+            .unwrap_or(self.nsid.as_str())
+    }
+
+    /// Photostream of the user
+    pub fn page_url(&self) -> String {
+        format!("{URL_PAGES}/photos/{}/", self.path())
+    }
+
+    /// Page of one of the user's albums
+    pub fn album_url(&self, id: impl Into<PhotosetId>) -> String {
+        format!("{URL_PAGES}/photos/{}/albums/{}", self.path(), id.into())
+    }
+}
+
+impl PhotoInfo {
+    /// Page of the photo
+    pub fn page_url(&self) -> String {
+        format!("{URL_PAGES}/photos/{}/{}/", self.owner.path(), self.id)
+    }
+
+    /// `https://flic.kr/p/...` link to the photo
+    pub fn short_url(&self) -> Option<String> {
+        self.id.short_url()
+    }
+}
+
+/// Sizes of the static images of a photo, named after the labels returned by
+/// [get_sizes](crate::PhotoRequestBuilder::get_sizes)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageSize {
+    /// 75px square
+    Square,
+    /// 150px square
+    LargeSquare,
+    /// 100px on the longest edge
+    Thumbnail,
+    /// 240px on the longest edge
+    Small,
+    Small320,
+    Small400,
+    /// 500px on the longest edge
+    #[default]
+    Medium,
+    Medium640,
+    Medium800,
+    /// 1024px on the longest edge
+    Large,
+    Large1600,
+    Large2048,
+    XLarge3K,
+    XLarge4K,
+    XLarge5K,
+    XLarge6K,
+    /// The file as uploaded, in its original format
+    Original,
+}
+
+impl ImageSize {
+    /// The suffix of the file name, `None` for [ImageSize::Medium] which has no suffix
+    pub fn suffix(&self) -> Option<&'static str> {
+        Some(match self {
+            ImageSize::Square => "s",
+            ImageSize::LargeSquare => "q",
+            ImageSize::Thumbnail => "t",
+            ImageSize::Small => "m",
+            ImageSize::Small320 => "n",
+            ImageSize::Small400 => "w",
+            ImageSize::Medium => return None,
+            ImageSize::Medium640 => "z",
+            ImageSize::Medium800 => "c",
+            ImageSize::Large => "b",
+            ImageSize::Large1600 => "h",
+            ImageSize::Large2048 => "k",
+            ImageSize::XLarge3K => "3k",
+            ImageSize::XLarge4K => "4k",
+            ImageSize::XLarge5K => "5k",
+            ImageSize::XLarge6K => "6k",
+            ImageSize::Original => "o",
+        })
+    }
+
+    /// The label flickr gives this size
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImageSize::Square => "Square",
+            ImageSize::LargeSquare => "Large Square",
+            ImageSize::Thumbnail => "Thumbnail",
+            ImageSize::Small => "Small",
+            ImageSize::Small320 => "Small 320",
+            ImageSize::Small400 => "Small 400",
+            ImageSize::Medium => "Medium",
+            ImageSize::Medium640 => "Medium 640",
+            ImageSize::Medium800 => "Medium 800",
+            ImageSize::Large => "Large",
+            ImageSize::Large1600 => "Large 1600",
+            ImageSize::Large2048 => "Large 2048",
+            ImageSize::XLarge3K => "X-Large 3K",
+            ImageSize::XLarge4K => "X-Large 4K",
+            ImageSize::XLarge5K => "X-Large 5K",
+            ImageSize::XLarge6K => "X-Large 6K",
+            ImageSize::Original => "Original",
+        }
+    }
+
+    /// Sizes from 1600px up are served with a secret of their own, which only getSizes returns
+    fn has_own_secret(&self) -> bool {
+        matches!(
+            self,
+            ImageSize::Large1600
+                | ImageSize::Large2048
+                | ImageSize::XLarge3K
+                | ImageSize::XLarge4K
+                | ImageSize::XLarge5K
+                | ImageSize::XLarge6K
+        )
+    }
+}
+
+/// Builder for the URL of a static image, without calling the API
+///
+/// ```
+/// # use flickr_api::urls::{ImageSize, PhotoUrl};
+/// let url = PhotoUrl::new("53421987654", "65535", "5f3a2b1c0d")
+///     .size(ImageSize::Large)
+///     .build();
+///
+/// assert_eq!(
+///     url.as_deref(),
+///     Some("https://live.staticflickr.com/65535/53421987654_5f3a2b1c0d_b.jpg")
+/// );
+/// ```
+///
+/// [ImageSize::Original] needs the original secret and format, which flickr only shares when
+/// the caller may download the original. Sizes from [ImageSize::Large1600] up have a secret of
+/// their own. [build](PhotoUrl::build) returns `None` for them,
+/// [get_sizes](crate::PhotoRequestBuilder::get_sizes) has to be used instead.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhotoUrl {
+    base: String,
+    id: PhotoId,
+    server: String,
+    secret: Secret,
+    original: Option<(Secret, String)>,
+    size: ImageSize,
+}
+
+impl PhotoUrl {
+    pub fn new(
+        id: impl Into<PhotoId>,
+        server: impl Into<String>,
+        secret: impl Into<Secret>,
+    ) -> Self {
+        PhotoUrl {
+            base: URL_STATIC.to_string(),
+            id: id.into(),
+            server: server.into(),
+            secret: secret.into(),
+            original: None,
+            size: ImageSize::default(),
+        }
+    }
+
+    /// Secret and format of the original file, e.g. `jpg`
+    pub fn original(mut self, secret: impl Into<Secret>, format: impl Into<String>) -> Self {
+        self.original = Some((secret.into(), format.into()));
+        self
+    }
+
+    pub fn size(mut self, size: ImageSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Serve the images from another host than `https://live.staticflickr.com`
+    pub fn base(mut self, base: impl Into<String>) -> Self {
+        self.base = base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn build(&self) -> Option<String> {
+        let PhotoUrl {
+            base, id, server, ..
+        } = self;
+
+        match (self.size, &self.original) {
+            (ImageSize::Original, Some((secret, format))) => {
+                Some(format!("{base}/{server}/{id}_{secret}_o.{format}"))
+            }
+            (ImageSize::Original, None) => None,
+            (size, _) if size.has_own_secret() => None,
+            (size, _) => {
+                let suffix = size.suffix().map(|s| format!("_{s}")).unwrap_or_default();
+                Some(format!("{base}/{server}/{id}_{}{suffix}.jpg", self.secret))
+            }
+        }
+    }
+}
+
+impl From<&PhotoInfo> for PhotoUrl {
+    fn from(info: &PhotoInfo) -> Self {
+        let url = PhotoUrl::new(&info.id, info.server.clone(), &info.secret);
+
+        match (&info.originalsecret, &info.originalformat) {
+            (Some(secret), Some(format)) => url.original(secret, format.clone()),
+            _ => url,
+        }
+    }
+}
+
+impl PhotoInfo {
+    /// URL of the image of the given size, see [PhotoUrl]
+    pub fn image_url(&self, size: ImageSize) -> Option<String> {
+        PhotoUrl::from(self).size(size).build()
+    }
+}
+
+#[test]
+fn test_base58() {
+    assert_eq!(encode_base58(0), "1");
+    assert_eq!(encode_base58(57), "Z");
+    assert_eq!(encode_base58(58), "21");
+    assert_eq!(encode_base58(3392387861), "6aLSHT");
+    assert_eq!(decode_base58("6aLSHT"), Some(3392387861));
+
+    for id in [1, 1234567890, 53421987654, u64::MAX] {
+        assert_eq!(decode_base58(&encode_base58(id)), Some(id));
+    }
+
+    // Characters left out of the alphabet
+    assert_eq!(decode_base58("0OIl"), None);
+    assert_eq!(decode_base58(""), None);
+    assert_eq!(decode_base58("zzzzzzzzzzzzzzzz"), None);
+}
+
+#[test]
+fn test_page_urls() {
+    let mut owner = Owner {
+        nsid: "98765432@N05".into(),
+        username: "lighthouse_keeper".into(),
+        realname: String::new(),
+        location: None,
+        iconserver: "0".into(),
+        iconfarm: 0,
+        path_alias: Some("lighthousekeeper".into()),
+    };
+
+    assert_eq!(
+        owner.page_url(),
+        "https://www.flickr.com/photos/lighthousekeeper/"
+    );
+    assert_eq!(
+        owner.album_url("72157626216528324"),
+        "https://www.flickr.com/photos/lighthousekeeper/albums/72157626216528324"
+    );
+
+    owner.path_alias = None;
+    assert_eq!(
+        owner.page_url(),
+        "https://www.flickr.com/photos/98765432@N05/"
+    );
+
+    let id = PhotoId::from("3392387861");
+    assert_eq!(id.short_url().as_deref(), Some("https://flic.kr/p/6aLSHT"));
+    assert_eq!(PhotoId::from("unknown").short_url(), None);
+}
+
+#[test]
+fn test_parse_urls() {
+    let alias = |name: &str| NameOrId::Alias(name.to_string());
+    let nsid = NameOrId::Id(UserId::from("98765432@N05"));
+
+    assert_eq!(
+        parse("https://flic.kr/p/6aLSHT"),
+        Some(FlickrUrl::Photo {
+            owner: None,
+            id: "3392387861".into()
+        })
+    );
+    assert_eq!(
+        parse("www.flickr.com/photos/lighthousekeeper/53421987654/in/dateposted/"),
+        Some(FlickrUrl::Photo {
+            owner: Some(alias("lighthousekeeper")),
+            id: "53421987654".into()
+        })
+    );
+    assert_eq!(
+        parse("https://www.flickr.com/photos/98765432@N05/"),
+        Some(FlickrUrl::User(nsid.clone()))
+    );
+    assert_eq!(
+        parse("https://www.flickr.com/photos/98765432@N05/sets/72157626216528324/"),
+        Some(FlickrUrl::Album {
+            owner: Some(nsid.clone()),
+            id: "72157626216528324".into()
+        })
+    );
+    assert_eq!(
+        parse("https://flickr.com/photos/lighthousekeeper/galleries/72157617483228192"),
+        Some(FlickrUrl::Gallery {
+            owner: alias("lighthousekeeper"),
+            id: "72157617483228192".into()
+        })
+    );
+    assert_eq!(
+        parse("https://www.flickr.com/groups/34427469792@N01/pool/"),
+        Some(FlickrUrl::Group(NameOrId::Id("34427469792@N01".into())))
+    );
+    assert_eq!(
+        parse("https://live.staticflickr.com/65535/53421987654_5f3a2b1c0d_b.jpg"),
+        Some(FlickrUrl::Photo {
+            owner: None,
+            id: "53421987654".into()
+        })
+    );
+
+    assert_eq!(parse("https://example.com/photos/someone/"), None);
+    assert_eq!(parse("https://flic.kr/p/0000"), None);
+    assert_eq!(parse("not a url"), None);
+}
+
+#[test]
+fn test_photo_url() {
+    let url = PhotoUrl::new("52987123456", "65535", "a1b2c3d4e5");
+
+    assert_eq!(
+        url.build().as_deref(),
+        Some("https://live.staticflickr.com/65535/52987123456_a1b2c3d4e5.jpg")
+    );
+    assert_eq!(
+        url.clone().size(ImageSize::LargeSquare).build().as_deref(),
+        Some("https://live.staticflickr.com/65535/52987123456_a1b2c3d4e5_q.jpg")
+    );
+    assert_eq!(url.clone().size(ImageSize::Large2048).build(), None);
+    assert_eq!(url.clone().size(ImageSize::Original).build(), None);
+    assert_eq!(
+        url.original("0f9e8d7c6b", "png")
+            .size(ImageSize::Original)
+            .base("http://127.0.0.1:8080/static/")
+            .build()
+            .as_deref(),
+        Some("http://127.0.0.1:8080/static/65535/52987123456_0f9e8d7c6b_o.png")
+    );
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_photo_url_matches_sizes() {
+    use crate::mock::{test_key, MockPhoto, MockServer};
+
+    let server = MockServer::start(test_key()).await;
+    let id = server.add_photo(MockPhoto::new(vec![]));
+    let client = server.logged_in(Permission::Read);
+
+    let info = client.photos().get_info(&id, None).await.unwrap();
+    let base = format!("http://{}/static", server.addr());
+
+    for size in client.photos().get_sizes(&id).await.unwrap() {
+        let size_type = match size.label.as_str() {
+            "Square" => ImageSize::Square,
+            "Thumbnail" => ImageSize::Thumbnail,
+            "Original" => ImageSize::Original,
+            other => panic!("Unexpected size: {other}"),
+        };
+        let url = PhotoUrl::from(&info).base(base.as_str()).size(size_type);
+
+        assert_eq!(url.build(), Some(size.source));
+    }
+}